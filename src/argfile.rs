@@ -0,0 +1,138 @@
+use anyhow::Result;
+use std::{fs, path::Path};
+
+/// Commands longer than this are passed to Java through an argfile.
+pub const ARGFILE_THRESHOLD: usize = 8000;
+
+pub fn command_len(cmd: &[String]) -> usize {
+    cmd.iter().map(|it| it.len() + 1).sum()
+}
+
+/// Quotes an argument for a JVM `@argfile`, which treats whitespace as a separator,
+/// `#` as a comment and backslashes inside quotes as escapes.
+pub fn quote_arg(arg: &str) -> String {
+    let needs_quotes = arg.is_empty()
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '#'));
+
+    if !needs_quotes {
+        return arg.into();
+    }
+
+    let escaped = arg.replace('\\', "\\\\").replace('"', "\\\"");
+
+    format!("\"{escaped}\"")
+}
+
+/// Writes one argument per line to a JVM `@argfile`.
+pub fn write_argfile(path: &Path, args: &[String]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut content = args
+        .iter()
+        .map(|it| quote_arg(it))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    content.push('\n');
+    fs::write(path, content)?;
+
+    Ok(())
+}
+
+/// Parses the arguments in a JVM `@argfile`, following the same rules as `java`: quotes
+/// group whitespace, backslashes escape inside quotes and can continue a quoted argument
+/// on the next line, and `#` starts a comment.
+pub fn parse_argfile(content: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+
+            Some(_) if c == '\\' => {
+                let escaped = match chars.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('f') => '\x0c',
+
+                    Some('\r' | '\n') => {
+                        while chars.next_if(|it| it.is_whitespace()).is_some() {}
+                        continue;
+                    }
+
+                    Some(other) => other,
+                    None => break,
+                };
+
+                current.get_or_insert_default().push(escaped);
+            }
+
+            Some(_) => current.get_or_insert_default().push(c),
+
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                current.get_or_insert_default();
+            }
+
+            None if c.is_whitespace() => args.extend(current.take()),
+
+            None if c == '#' && current.is_none() => {
+                while chars.next_if(|it| *it != '\n').is_some() {}
+            }
+
+            None => current.get_or_insert_default().push(c),
+        }
+    }
+
+    args.extend(current);
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn round_trip() {
+        let args = [
+            "-cp",
+            "/home/me/Minecraft Servers/libs/a.jar:/home/me/Minecraft Servers/libs/b.jar",
+            "--name=#1",
+            "say \"hi\" it's me",
+            "",
+            r"C:\Users\Me\AppData\Roaming\.minecraft\libraries\foo.jar",
+            r"\\server\share\",
+            "plain",
+        ]
+        .map(String::from);
+
+        let content = args.iter().map(|it| quote_arg(it)).join("\n");
+
+        assert_eq!(parse_argfile(&content), args);
+    }
+
+    #[test]
+    fn plain_args_arent_quoted() {
+        assert_eq!(quote_arg("-Xmx4G"), "-Xmx4G");
+        assert_eq!(quote_arg(""), "\"\"");
+    }
+
+    #[test]
+    fn comments_and_continuations() {
+        let content = "# Comment\n-p libraries/a.jar # trailing\n\"continued \\\n    line\" 'single # quoted'\n";
+
+        assert_eq!(
+            parse_argfile(content),
+            ["-p", "libraries/a.jar", "continued line", "single # quoted"]
+        );
+    }
+}
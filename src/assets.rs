@@ -0,0 +1,125 @@
+use crate::{
+    download::{Download, download_all},
+    manifest::{AssetIndexInfo, LoggingConfig},
+    util::make_path_and_create,
+};
+use anyhow::Result;
+use itertools::Itertools;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const RESOURCES_URL: &str = "https://resources.download.minecraft.net";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AssetIndex {
+    pub objects: HashMap<String, AssetObject>,
+    /// Legacy versions read assets by name from `assets/virtual/<id>`.
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    /// Very old versions read assets by name from `resources` in the game directory.
+    #[serde(default)]
+    pub map_to_resources: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetObject {
+    pub hash: String,
+    pub size: u64,
+}
+
+impl AssetObject {
+    /// The object's path relative to `assets/objects`.
+    pub fn path(&self) -> String {
+        format!("{}/{}", &self.hash[..2], self.hash)
+    }
+}
+
+/// Downloads an asset index and all of its objects into `<game_dir>/assets`, laying out
+/// legacy assets by name where the index asks for it.
+pub fn download_assets(game_dir: &Path, info: &AssetIndexInfo) -> Result<()> {
+    let assets_dir = game_dir.join("assets");
+    let objects_dir = assets_dir.join("objects");
+
+    let index = Download {
+        path: assets_dir.join("indexes").join(format!("{}.json", info.id)),
+        urls: vec![info.url.clone()],
+        sha1: Some(info.sha1.clone()),
+    };
+
+    if let Some((_, err)) = download_all(std::slice::from_ref(&index))?.pop() {
+        return Err(err.context(format!("Failed to download asset index {}", info.id)));
+    }
+
+    let index = serde_json::from_str::<AssetIndex>(&fs::read_to_string(&index.path)?)?;
+
+    let downloads = index
+        .objects
+        .values()
+        .unique_by(|it| &it.hash)
+        .map(|object| Download {
+            path: objects_dir.join(object.path()),
+            urls: vec![format!("{RESOURCES_URL}/{}", object.path())],
+            sha1: Some(object.hash.clone()),
+        })
+        .collect_vec();
+
+    let failed = download_all(&downloads)?;
+
+    if !failed.is_empty() {
+        for (_, err) in &failed {
+            eprintln!("{err}");
+        }
+
+        return Err(anyhow!("Failed to download {} assets!", failed.len()));
+    }
+
+    let legacy_dir = match (index.map_to_resources, index.is_virtual) {
+        (true, _) => Some(game_dir.join("resources")),
+        (_, true) => Some(assets_dir.join("virtual").join(&info.id)),
+        _ => None,
+    };
+
+    if let Some(legacy_dir) = legacy_dir {
+        for (name, object) in &index.objects {
+            let path = legacy_dir.join(name);
+
+            if !path.exists() {
+                make_path_and_create(path.parent().unwrap())?;
+                fs::copy(objects_dir.join(object.path()), path)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads a client logging config into `<game_dir>/assets/log_configs` and returns the
+/// JVM argument that enables it.
+pub fn download_log_config(game_dir: &Path, config: &LoggingConfig) -> Result<String> {
+    let path: PathBuf = game_dir
+        .join("assets")
+        .join("log_configs")
+        .join(&config.file.id);
+
+    let download = Download {
+        path,
+        urls: vec![config.file.url.clone()],
+        sha1: Some(config.file.sha1.clone()),
+    };
+
+    if let Some((_, err)) = download_all(std::slice::from_ref(&download))?.pop() {
+        return Err(err.context(format!(
+            "Failed to download logging config {}",
+            config.file.id
+        )));
+    }
+
+    Ok(config
+        .argument
+        .replace("${path}", download.path.to_str().unwrap()))
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+use zip::ZipArchive;
+
+pub const BUNDLER_EXTRACT_TASK: &str = "BUNDLER_EXTRACT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundlerExtractMode {
+    /// Extract both `versions/` and `libraries/` into the output directory.
+    All,
+    /// Extract every library into the output directory.
+    Libraries,
+    /// Extract the single server jar to the output file.
+    JarOnly,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundlerEntry {
+    pub sha256: String,
+    pub id: String,
+    pub path: String,
+}
+
+pub fn read_bundler_list(zip: &mut ZipArchive<File>, list: &str) -> Result<Vec<BundlerEntry>> {
+    let mut entry = zip
+        .by_name(&format!("META-INF/{list}.list"))
+        .map_err(|_| anyhow!("Not a bundler jar, missing META-INF/{list}.list"))?;
+
+    let mut content = String::new();
+
+    entry.read_to_string(&mut content)?;
+
+    content
+        .lines()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|line| {
+            let mut parts = line.split('\t');
+
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(sha256), Some(id), Some(path)) => Ok(BundlerEntry {
+                    sha256: sha256.to_lowercase(),
+                    id: id.into(),
+                    path: path.into(),
+                }),
+
+                _ => Err(anyhow!("Invalid line in META-INF/{list}.list: {line}")),
+            }
+        })
+        .collect()
+}
+
+pub fn extract_bundler(input: &Path, output: &Path, mode: BundlerExtractMode) -> Result<()> {
+    let mut zip = ZipArchive::new(File::open(input)?)?;
+
+    match mode {
+        BundlerExtractMode::JarOnly => {
+            let versions = read_bundler_list(&mut zip, "versions")?;
+
+            let [version] = versions.as_slice() else {
+                return Err(anyhow!(
+                    "Expected exactly one entry in META-INF/versions.list, found {}",
+                    versions.len()
+                ));
+            };
+
+            extract_entry(&mut zip, "versions", version, output)?;
+        }
+
+        BundlerExtractMode::Libraries => {
+            for lib in read_bundler_list(&mut zip, "libraries")? {
+                extract_entry(&mut zip, "libraries", &lib, &output.join(&lib.path))?;
+            }
+        }
+
+        BundlerExtractMode::All => {
+            for list in ["versions", "libraries"] {
+                for it in read_bundler_list(&mut zip, list)? {
+                    extract_entry(&mut zip, list, &it, &output.join(list).join(&it.path))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_entry(
+    zip: &mut ZipArchive<File>,
+    list: &str,
+    entry: &BundlerEntry,
+    target: &Path,
+) -> Result<()> {
+    if target.exists() && sha256_hex(&fs::read(target)?) == entry.sha256 {
+        return Ok(());
+    }
+
+    let name = format!("META-INF/{list}/{}", entry.path);
+
+    let mut file = zip
+        .by_name(&name)
+        .map_err(|_| anyhow!("Bundler jar is missing entry: {name}"))?;
+
+    let mut content = Vec::new();
+
+    file.read_to_end(&mut content)?;
+
+    let hash = sha256_hex(&content);
+
+    if hash != entry.sha256 {
+        return Err(anyhow!(
+            "Hash mismatch for {} ({name}): expected {}, got {hash}",
+            entry.id,
+            entry.sha256
+        ));
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(target, content)?;
+
+    Ok(())
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Runs installertools' `BUNDLER_EXTRACT` task with its command-line arguments.
+pub fn run_bundler_extract(args: &[String], work_dir: &Path) -> Result<()> {
+    let mut input = None;
+    let mut output = None;
+    let mut mode = BundlerExtractMode::All;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--task" => {
+                args.next();
+            }
+
+            "--input" => input = args.next(),
+            "--output" => output = args.next(),
+            "--all" => mode = BundlerExtractMode::All,
+            "--libraries" => mode = BundlerExtractMode::Libraries,
+            "--jar-only" => mode = BundlerExtractMode::JarOnly,
+            other => return Err(anyhow!("Unknown {BUNDLER_EXTRACT_TASK} argument: {other}")),
+        }
+    }
+
+    let input = input.ok_or(anyhow!("{BUNDLER_EXTRACT_TASK} requires --input"))?;
+    let output = output.ok_or(anyhow!("{BUNDLER_EXTRACT_TASK} requires --output"))?;
+
+    extract_bundler(&work_dir.join(input), &work_dir.join(output), mode)
+}
@@ -1,24 +1,25 @@
 use crate::{
-    download::download_if_needed,
-    libraries::download_libs,
-    manifest::download_mc_jars,
-    maven::maven_to_path,
-    processors::run_processor,
-    profile::{NeoProfile, NeoVersionManifest, setup_vars},
-    side::Side,
-    util::make_path_and_create,
+    config::Config,
+    install::{InstallOptions, JvmOptions, install},
+    java::JavaOptions,
+    launch::{LaunchOptions, launch},
+    plan::{PlanFormat, plan},
+    processors::ProcessorMode,
+    rules::Platform,
+    server::{RunOptions, ServerOptions, run_server},
+    side::InstallSide,
+    signal::install_handler,
+    smoke::{ReportFormat, SmokeTestOptions, smoke_test},
 };
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use indicatif::ProgressIterator;
+use clap::{Args, Parser, Subcommand};
 use libsui::find_section;
 use std::{
     env::current_exe,
     fs::{self, File},
-    io::Read,
     path::PathBuf,
+    time::Duration,
 };
-use zip::ZipArchive;
 
 pub const NEO_MAVEN: &str = "https://maven.neoforged.net/releases";
 pub const EMBEDDED_VERSION_SECTION: &str = "__neo_version";
@@ -35,22 +36,77 @@ pub struct Cli {
 pub struct AutoCli {
     /// The side to install for.
     #[clap(short = 's', long = "side", value_enum)]
-    side: Side,
+    pub side: InstallSide,
 
-    /// The target path to install to.
+    #[command(flatten)]
+    pub args: InstallArgs,
+}
+
+#[derive(Args)]
+pub struct InstallArgs {
+    /// The target path to install to. With `--side both`, each side is installed into a
+    /// `client` or `server` subdirectory.
     #[clap(short = 'd', long = "dir", default_value = ".")]
-    target: PathBuf,
+    pub target: PathBuf,
 
     /// Keep files created during installation.
     #[clap(short = 'k', long = "keep")]
-    keep: bool,
+    pub keep: bool,
+
+    /// How to run processors: natively in Rust, with Java, or natively where possible.
+    #[clap(short = 'p', long = "processors", value_enum, default_value_t)]
+    pub processor_mode: ProcessorMode,
+
+    /// Stream processor output to the terminal as well as the log files.
+    #[clap(short = 'v', long = "verbose")]
+    pub verbose: bool,
+
+    /// The maximum number of seconds a single processor may run for.
+    #[clap(short = 't', long = "timeout")]
+    pub timeout: Option<u64>,
+
+    /// Always pass processor arguments to Java through an @argfile.
+    #[clap(short = 'a', long = "argfile")]
+    pub argfile: bool,
+
+    /// The maximum number of processors to run at once.
+    #[clap(short = 'J', long = "jobs", default_value_t = 1)]
+    pub jobs: usize,
+
+    /// Run Java processors in a sandbox without network access that can only write to the
+    /// installer directory and their declared outputs (Linux only).
+    #[clap(long = "sandbox")]
+    pub sandbox: bool,
+
+    /// The OS to pick libraries and natives for, instead of the current one.
+    #[clap(long = "os", value_parser = ["windows", "osx", "linux"])]
+    pub os: Option<String>,
+
+    /// The architecture to pick libraries and natives for, instead of the current one.
+    #[clap(long = "arch", value_parser = ["x86", "x86_64", "arm32", "arm64"])]
+    pub arch: Option<String>,
+
+    /// The OS version rules are matched against, as Java reports it (e.g. 10.0 for
+    /// Windows 10 and 11). Defaults to the current one unless --os picks another OS.
+    #[clap(long = "os-version", value_name = "VERSION")]
+    pub os_version: Option<String>,
+
+    /// A TOML config file with extra settings.
+    #[clap(short = 'c', long = "config")]
+    pub config: Option<PathBuf>,
 
-    /// The path to the Java executable to use when running processors.
-    #[clap(short = 'j', long = "java", default_value = "java")]
-    java: String,
+    #[command(flatten)]
+    pub java: JavaOptions,
+
+    #[command(flatten)]
+    pub jvm: JvmOptions,
+
+    #[command(flatten)]
+    pub server: ServerOptions,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Generate an automated installer for a specific version.
     #[clap(alias = "g")]
@@ -67,26 +123,150 @@ pub enum Commands {
     /// Install NeoForge.
     #[clap(alias = "i")]
     Install {
+        /// The version of NeoForge to install.
+        #[clap(short = 'n', long = "neo")]
+        neo: String,
+
         /// The side to install for.
         #[clap(short = 's', long = "side", value_enum)]
-        side: Side,
+        side: InstallSide,
 
-        /// The version of NeoForge to install.
+        #[command(flatten)]
+        args: InstallArgs,
+    },
+
+    /// Launch an installed NeoForge client with an offline account.
+    #[clap(alias = "l")]
+    Launch {
+        /// The installed version of NeoForge to launch.
         #[clap(short = 'n', long = "neo")]
         neo: String,
 
-        /// The target path to install to.
+        /// The game directory the client was installed to.
         #[clap(short = 'd', long = "dir", default_value = ".")]
-        target: PathBuf,
+        game_dir: PathBuf,
+
+        /// The offline username to play as.
+        #[clap(short = 'u', long = "username", default_value = "Player")]
+        username: String,
+
+        /// Launch the game in demo mode.
+        #[clap(long = "demo")]
+        demo: bool,
+
+        /// The width of the game window.
+        #[clap(long = "width", requires = "height")]
+        width: Option<u32>,
 
-        /// Keep files created during installation.
-        #[clap(short = 'k', long = "keep")]
-        keep: bool,
+        /// The height of the game window.
+        #[clap(long = "height", requires = "width")]
+        height: Option<u32>,
 
-        /// The path to the Java executable to use when running processors.
-        #[clap(short = 'j', long = "java", default_value = "java")]
-        java: String,
+        #[command(flatten)]
+        java: JavaOptions,
+
+        #[command(flatten)]
+        jvm: JvmOptions,
+    },
+
+    /// Run an installed NeoForge server.
+    #[clap(alias = "r")]
+    Run {
+        /// The installed version of NeoForge to run. Only needed if the server directory
+        /// has more than one.
+        #[clap(short = 'n', long = "neo")]
+        neo: Option<String>,
+
+        /// The directory the server was installed to.
+        #[clap(short = 'd', long = "dir", default_value = ".")]
+        dir: PathBuf,
+
+        #[command(flatten)]
+        java: JavaOptions,
+
+        /// Run the server without its GUI.
+        #[clap(long = "nogui")]
+        nogui: bool,
+
+        /// Extra arguments to pass to the server, after `--`.
+        #[clap(last = true)]
+        args: Vec<String>,
     },
+
+    /// Install (or reuse) a server, check that it starts, then stop it.
+    SmokeTest {
+        /// The version of NeoForge to test.
+        #[clap(short = 'n', long = "neo")]
+        neo: String,
+
+        /// The maximum number of seconds to wait for the server to start.
+        #[clap(long = "startup-timeout", default_value_t = 300)]
+        startup_timeout: u64,
+
+        /// Install the server again, even if it's already installed.
+        #[clap(long = "reinstall")]
+        reinstall: bool,
+
+        /// A file to write the result to.
+        #[clap(short = 'r', long = "report")]
+        report: Option<PathBuf>,
+
+        /// The format of the report.
+        #[clap(short = 'f', long = "report-format", value_enum, default_value_t)]
+        report_format: ReportFormat,
+
+        #[command(flatten)]
+        args: InstallArgs,
+    },
+
+    /// Print everything an install would do without running it.
+    Plan {
+        /// The version of NeoForge to plan an install of.
+        #[clap(short = 'n', long = "neo")]
+        neo: String,
+
+        /// The side to plan an install for.
+        #[clap(short = 's', long = "side", value_enum)]
+        side: InstallSide,
+
+        /// How to print the plan.
+        #[clap(short = 'f', long = "format", value_enum, default_value_t)]
+        format: PlanFormat,
+
+        #[command(flatten)]
+        args: InstallArgs,
+    },
+}
+
+impl InstallArgs {
+    pub fn into_options(self, neo: String, side: InstallSide) -> Result<InstallOptions> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        let server = config.server.merge(self.server);
+
+        // Catch bad properties before the install rather than after it.
+        server.properties()?;
+
+        Ok(InstallOptions {
+            neo,
+            side,
+            target: self.target,
+            keep: self.keep,
+            java: config.java.merge(self.java),
+            processor_mode: self.processor_mode,
+            verbose: self.verbose,
+            timeout: self.timeout.map(Duration::from_secs),
+            argfile: self.argfile,
+            jobs: self.jobs,
+            sandbox: self.sandbox,
+            platform: Platform::with_overrides(self.os, self.arch, self.os_version),
+            jvm: config.jvm.merge(self.jvm),
+            server,
+        })
+    }
 }
 
 impl AutoCli {
@@ -103,11 +283,9 @@ impl AutoCli {
 
         Cli {
             command: Commands::Install {
-                side: self.side,
                 neo,
-                target: self.target,
-                keep: self.keep,
-                java: self.java,
+                side: self.side,
+                args: self.args,
             },
         }
         .run()
@@ -151,91 +329,78 @@ impl Cli {
                 }
             }
 
-            Commands::Install {
-                side,
+            Commands::Install { neo, side, args } => {
+                install(&args.into_options(neo, side)?).await?
+            }
+
+            Commands::Launch {
                 neo,
-                target,
-                keep,
+                game_dir,
+                username,
                 java,
+                demo,
+                width,
+                height,
+                jvm,
             } => {
-                let work_dir = std::path::absolute(make_path_and_create(target)?)?;
-                let base_path = work_dir.join(".installer");
-                let lib_path = work_dir.join("libraries");
-                let data_path = base_path.join("data");
-                let jar_path = base_path.join("installer.jar");
-                let jar_artifact = format!("net.neoforged:neoforge:{}:installer", neo);
-                let jar_url = format!("{NEO_MAVEN}/{}", maven_to_path(jar_artifact));
-
-                download_if_needed(&jar_path, jar_url).await?;
-
-                let jar_path = jar_path.canonicalize()?;
-                let jar_file = File::open(&jar_path)?;
-                let mut jar_zip = ZipArchive::new(jar_file)?;
-                let mut profile_entry = jar_zip.by_name("install_profile.json")?;
-                let mut profile_json = String::new();
-
-                profile_entry.read_to_string(&mut profile_json)?;
-
-                drop(profile_entry);
-
-                let mut version_entry = jar_zip.by_name("version.json")?;
-                let mut version_json = String::new();
-
-                version_entry.read_to_string(&mut version_json)?;
-
-                drop(version_entry);
-
-                let mut data_files = Vec::new();
-
-                for entry in jar_zip.file_names() {
-                    if entry.starts_with("data/") && entry != "data/" {
-                        data_files.push(entry.to_string());
-                    }
-                }
-
-                if data_files.len() > 0 {
-                    fs::create_dir_all(data_path)?;
-
-                    for file in data_files.iter().progress() {
-                        let mut entry = jar_zip.by_name(file)?;
-                        let mut content = Vec::new();
-
-                        entry.read_to_end(&mut content)?;
-
-                        fs::write(base_path.join(file), content)?;
-                    }
-                }
-
-                let mut profile = serde_json::from_str::<NeoProfile>(&profile_json)?;
-                let version_json = serde_json::from_str::<NeoVersionManifest>(&version_json)?;
-
-                profile.add_minecraft();
-                profile.libraries.extend(version_json.libraries);
-
-                let vars = setup_vars(&profile, side, &lib_path, &base_path, &jar_path);
-
-                download_libs(&profile, &lib_path).await?;
-                download_mc_jars(&profile, &vars, side, &lib_path, &base_path).await?;
-
-                for proc in &profile.processors {
-                    if let Some(sides) = &proc.sides {
-                        if !sides.contains(&side) {
-                            eprintln!(
-                                "Processor skipped due to being on the wrong side: {}",
-                                proc.jar
-                            );
-
-                            continue;
-                        }
-                    }
+                launch(&LaunchOptions {
+                    neo,
+                    game_dir,
+                    username,
+                    java,
+                    demo,
+                    resolution: width.zip(height),
+                    jvm,
+                })
+                .await?
+            }
 
-                    run_processor(proc, &vars, &lib_path, &work_dir, &java).await?;
-                }
+            Commands::Run {
+                neo,
+                dir,
+                java,
+                nogui,
+                args,
+            } => {
+                run_server(&RunOptions {
+                    dir,
+                    neo,
+                    java,
+                    nogui,
+                    args,
+                })
+                .await?
+            }
 
-                if !keep {
-                    fs::remove_dir_all(base_path)?;
+            Commands::SmokeTest {
+                neo,
+                startup_timeout,
+                reinstall,
+                report,
+                report_format,
+                args,
+            } => {
+                let report = smoke_test(&SmokeTestOptions {
+                    install: args.into_options(neo, InstallSide::Server)?,
+                    startup_timeout: Duration::from_secs(startup_timeout),
+                    reinstall,
+                    report,
+                    report_format,
+                })
+                .await?;
+
+                match report.passed {
+                    true => println!("Smoke test passed: {}.", report.message),
+                    false => return Err(anyhow!("Smoke test failed: {}!", report.message)),
                 }
             }
+
+            Commands::Plan {
+                neo,
+                side,
+                format,
+                args,
+            } => plan(&args.into_options(neo, side)?).await?.print(format)?,
         }
 
         Ok(())
@@ -249,6 +414,8 @@ pub fn is_auto() -> bool {
 }
 
 pub async fn run() -> Result<()> {
+    install_handler()?;
+
     match is_auto() {
         true => AutoCli::exec().await,
         false => Cli::exec().await,
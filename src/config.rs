@@ -0,0 +1,22 @@
+use crate::{install::JvmOptions, java::JavaOptions, server::ServerOptions};
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+/// Settings loaded from a TOML file passed with `--config`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Config {
+    pub jvm: JvmOptions,
+    pub java: JavaOptions,
+    pub server: ServerOptions,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+}
@@ -1,5 +1,10 @@
+use crate::signal::interrupted;
 use anyhow::Result;
-use std::{fs, path::PathBuf};
+use indicatif::{ParallelProgressIterator, ProgressStyle};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use reqwest::{StatusCode, blocking::Client};
+use sha1::{Digest, Sha1};
+use std::{fs, path::PathBuf, sync::OnceLock};
 
 pub async fn download_if_needed(path: impl Into<PathBuf>, url: impl AsRef<str>) -> Result<()> {
     let path = path.into();
@@ -14,3 +19,120 @@ pub async fn download_if_needed(path: impl Into<PathBuf>, url: impl AsRef<str>)
 
     Ok(())
 }
+
+/// A file to download from the first of its URLs that works.
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub path: PathBuf,
+    pub urls: Vec<String>,
+    /// The expected SHA-1, checked for both cached and downloaded files when known.
+    pub sha1: Option<String>,
+}
+
+/// The HTTP client shared by every download, so connections get reused.
+fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+
+    CLIENT.get_or_init(Client::new)
+}
+
+pub fn sha1_hex(data: &[u8]) -> String {
+    Sha1::digest(data)
+        .iter()
+        .map(|it| format!("{it:02x}"))
+        .collect()
+}
+
+impl Download {
+    fn matches(&self, data: &[u8]) -> bool {
+        self.sha1
+            .as_ref()
+            .is_none_or(|it| it.eq_ignore_ascii_case(&sha1_hex(data)))
+    }
+
+    fn is_cached(&self) -> Result<bool> {
+        if !fs::exists(&self.path)? {
+            return Ok(false);
+        }
+
+        Ok(self.sha1.is_none() || self.matches(&fs::read(&self.path)?))
+    }
+
+    fn fetch(&self) -> Result<()> {
+        if self.urls.is_empty() {
+            return Err(anyhow!(
+                "{} is missing and can't be downloaded",
+                self.path.display()
+            ));
+        }
+
+        let mut errors = Vec::new();
+
+        for url in &self.urls {
+            let req = match client().get(url).send() {
+                Ok(req) => req,
+
+                Err(err) => {
+                    errors.push(format!("{url} ({err})"));
+                    continue;
+                }
+            };
+
+            if req.status() != StatusCode::OK {
+                errors.push(format!("{url} ({})", req.status()));
+                continue;
+            }
+
+            let data = match req.bytes() {
+                Ok(data) => data,
+
+                Err(err) => {
+                    errors.push(format!("{url} ({err})"));
+                    continue;
+                }
+            };
+
+            if !self.matches(&data) {
+                errors.push(format!("{url} (hash mismatch)"));
+                continue;
+            }
+
+            fs::create_dir_all(self.path.parent().unwrap())?;
+            fs::write(&self.path, data)?;
+
+            return Ok(());
+        }
+
+        Err(anyhow!("Failed to download from: {}", errors.join(", ")))
+    }
+}
+
+/// Downloads files in parallel, skipping ones that are already cached. Returns the
+/// downloads that failed, so callers can decide whether that's fatal.
+pub fn download_all(downloads: &[Download]) -> Result<Vec<(Download, anyhow::Error)>> {
+    let style = ProgressStyle::default_bar()
+        .progress_chars("=> ")
+        .template("{msg} [{wide_bar:.cyan/blue}] {percent}% {pos:>7}/{len:7}")?;
+
+    // Rayon runs single-item iterators on the calling thread, which may be a Tokio worker
+    // where blocking requests aren't allowed. A scope always runs inside the pool.
+    let failed = rayon::scope(|_| {
+        downloads
+            .par_iter()
+            .progress_with_style(style)
+            .filter_map(|download| {
+                if interrupted() {
+                    return None;
+                }
+
+                download
+                    .is_cached()
+                    .and_then(|cached| if cached { Ok(()) } else { download.fetch() })
+                    .err()
+                    .map(|err| (download.clone(), err))
+            })
+            .collect()
+    });
+
+    Ok(failed)
+}
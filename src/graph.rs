@@ -0,0 +1,211 @@
+use crate::{
+    maven::maven_to_path,
+    placeholders::{replace_tokens, resolve_arg},
+    processors::{Processor, ProcessorContext, run_processor},
+    signal::check_interrupted,
+};
+use anyhow::Result;
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+use tokio::task::JoinSet;
+
+/// Arguments whose value is a path the processor writes to.
+pub const OUTPUT_FLAGS: &[&str] = &[
+    "--output",
+    "--out",
+    "--to",
+    "--slim",
+    "--extra",
+    "--srg",
+    "--extract-libraries-to",
+];
+
+/// Output arguments whose value is a directory rather than a file.
+pub const OUTPUT_DIR_FLAGS: &[&str] = &["--extract-libraries-to"];
+
+/// The files and directories a processor reads and writes.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessorIo {
+    pub inputs: Vec<PathBuf>,
+    pub outputs: Vec<PathBuf>,
+    /// The outputs that are directories, from [`OUTPUT_DIR_FLAGS`] or a trailing `/`.
+    pub output_dirs: Vec<PathBuf>,
+}
+
+fn normalize(work_dir: &Path, path: impl AsRef<Path>) -> PathBuf {
+    work_dir
+        .join(path)
+        .components()
+        .filter(|it| *it != Component::CurDir)
+        .collect()
+}
+
+fn overlaps(a: &[PathBuf], b: &[PathBuf]) -> bool {
+    a.iter()
+        .any(|a| b.iter().any(|b| a.starts_with(b) || b.starts_with(a)))
+}
+
+impl ProcessorIo {
+    pub fn of(proc: &Processor, ctx: &ProcessorContext) -> Result<Self> {
+        let mut io = Self::default();
+        let path = |it: &str| normalize(&ctx.work_dir, it);
+
+        io.inputs.push(path(&library_path(ctx, &proc.jar)));
+
+        for item in &proc.classpath {
+            io.inputs.push(path(&library_path(ctx, item)));
+        }
+
+        for (i, arg) in proc.args.iter().enumerate() {
+            if !arg.contains('{') && !arg.starts_with('[') {
+                continue;
+            }
+
+            let resolved = resolve_arg(&ctx.vars, &ctx.lib_path, arg)?;
+            let value = path(&resolved);
+            let flag = i.checked_sub(1).map(|it| proc.args[it].as_str());
+
+            if flag.is_some_and(|it| OUTPUT_FLAGS.contains(&it)) {
+                if flag.is_some_and(|it| OUTPUT_DIR_FLAGS.contains(&it))
+                    || resolved.ends_with(['/', '\\'])
+                {
+                    io.output_dirs.push(value.clone());
+                }
+
+                io.outputs.push(value);
+            } else {
+                io.inputs.push(value);
+            }
+        }
+
+        for key in proc.outputs.iter().flat_map(|it| it.keys()) {
+            io.outputs.push(path(&replace_tokens(&ctx.vars, key)?));
+        }
+
+        Ok(io)
+    }
+
+    /// Whether `later` has to wait for `self` to finish.
+    pub fn conflicts_with(&self, later: &ProcessorIo) -> bool {
+        overlaps(&self.outputs, &later.inputs)
+            || overlaps(&self.outputs, &later.outputs)
+            || overlaps(&self.inputs, &later.outputs)
+    }
+}
+
+fn library_path(ctx: &ProcessorContext, coord: &str) -> String {
+    ctx.lib_path
+        .join(maven_to_path(coord))
+        .to_str()
+        .unwrap()
+        .into()
+}
+
+/// Works out which earlier processors each processor depends on. Processors whose outputs
+/// can't be determined depend on, and are depended on by, everything before them.
+pub fn build_graph(
+    processors: &[(usize, Processor)],
+    ctx: &ProcessorContext,
+) -> Result<Vec<BTreeSet<usize>>> {
+    let io = processors
+        .iter()
+        .map(|(_, proc)| ProcessorIo::of(proc, ctx))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((0..processors.len())
+        .map(|later| {
+            (0..later)
+                .filter(|&earlier| {
+                    io[earlier].outputs.is_empty()
+                        || io[later].outputs.is_empty()
+                        || io[earlier].conflicts_with(&io[later])
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Runs processors with up to `jobs` at once, starting each one only after everything it
+/// depends on has finished. Ready processors start in their declared order. Each group has
+/// its own context, like a side of the install, and only depends on processors in the same
+/// group, so groups run alongside each other under the same `jobs` limit.
+pub async fn run_processors(
+    groups: Vec<(Vec<(usize, Processor)>, ProcessorContext)>,
+    jobs: usize,
+) -> Result<()> {
+    let mut deps = Vec::new();
+    let mut processors = Vec::new();
+
+    for (group, ctx) in groups {
+        let offset = processors.len();
+        let ctx = Arc::new(ctx);
+
+        deps.extend(build_graph(&group, &ctx)?.into_iter().map(|it| {
+            it.into_iter()
+                .map(|dep| dep + offset)
+                .collect::<BTreeSet<_>>()
+        }));
+
+        processors.extend(group.into_iter().map(|it| Arc::new((it, ctx.clone()))));
+    }
+
+    let mut pending = (0..processors.len()).collect::<BTreeSet<_>>();
+    let mut running = JoinSet::new();
+    let mut error = None;
+
+    while !pending.is_empty() || !running.is_empty() {
+        while error.is_none() && running.len() < jobs.max(1) {
+            let Some(next) = pending.iter().copied().find(|it| deps[*it].is_empty()) else {
+                break;
+            };
+
+            if let Err(err) = check_interrupted() {
+                error = Some(err);
+                break;
+            }
+
+            pending.remove(&next);
+
+            let proc = processors[next].clone();
+
+            running.spawn(async move {
+                let ((index, proc), ctx) = &*proc;
+
+                (next, run_processor(proc, *index, ctx).await)
+            });
+        }
+        let Some(done) = running.join_next().await else {
+            if error.is_none() && !pending.is_empty() {
+                error = Some(anyhow!("Processor dependency graph has a cycle!"));
+            }
+
+            break;
+        };
+
+        // A failed or panicked task stops new ones from starting, but the loop keeps joining
+        // the rest so no processor is left running.
+        match done {
+            Ok((done, Ok(()))) => {
+                for it in deps.iter_mut() {
+                    it.remove(&done);
+                }
+            }
+
+            Ok((_, Err(err))) => {
+                error.get_or_insert(err);
+            }
+
+            Err(err) => {
+                error.get_or_insert(anyhow!("A processor task failed: {err}"));
+            }
+        }
+    }
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,484 @@
+use crate::{
+    assets::{download_assets, download_log_config},
+    cli::NEO_MAVEN,
+    download::download_if_needed,
+    graph::run_processors,
+    java::{JAVA_EXE, JavaOptions},
+    launcher::install_client,
+    launcher::natives_dir,
+    libraries::{Library, download_libs, extract_natives, link_libs},
+    manifest::{VersionManifest, download_mc_jars, fetch_version_manifest},
+    maven::maven_to_path,
+    native::find_native,
+    processors::{Processor, ProcessorContext, ProcessorMode},
+    profile::{NeoProfile, NeoVersionManifest, setup_vars},
+    rules::Platform,
+    server::{
+        ServerOptions, restore_scripts, save_scripts, write_server_config, write_server_files,
+    },
+    side::{InstallSide, Side},
+    signal::check_interrupted,
+    util::{clean_installer_dir, make_path_and_create},
+};
+use anyhow::Result;
+use clap::Args;
+use indicatif::ProgressIterator;
+use itertools::Itertools;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use zip::ZipArchive;
+
+/// Extra JVM settings used when running processors.
+#[derive(Debug, Clone, Default, Args, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct JvmOptions {
+    /// The maximum heap size for processors, passed as -Xmx (e.g. 2G).
+    #[clap(long = "max-memory", value_name = "SIZE")]
+    pub max_memory: Option<String>,
+
+    /// An HTTP(S) proxy for processors to use.
+    #[clap(long = "proxy", value_name = "HOST:PORT")]
+    pub proxy: Option<String>,
+
+    /// The temporary directory for processors, passed as -Djava.io.tmpdir.
+    #[clap(long = "tmp-dir", value_name = "DIR")]
+    pub tmp_dir: Option<PathBuf>,
+
+    /// Extra --add-opens values for processors (e.g. java.base/java.lang=ALL-UNNAMED).
+    #[clap(long = "add-opens", value_name = "MODULE/PACKAGE=TARGET")]
+    pub add_opens: Vec<String>,
+
+    /// Extra arguments to pass to the JVM when running processors.
+    #[clap(long = "jvm-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub jvm_args: Vec<String>,
+
+    /// Extra environment variables for processors.
+    #[clap(long = "env", value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+}
+
+impl JvmOptions {
+    /// Merges two sets of options, with `other` taking priority.
+    pub fn merge(self, other: JvmOptions) -> Self {
+        Self {
+            max_memory: other.max_memory.or(self.max_memory),
+            proxy: other.proxy.or(self.proxy),
+            tmp_dir: other.tmp_dir.or(self.tmp_dir),
+            add_opens: [self.add_opens, other.add_opens].concat(),
+            jvm_args: [self.jvm_args, other.jvm_args].concat(),
+            env: [self.env, other.env].concat(),
+        }
+    }
+
+    pub fn args(&self) -> Result<Vec<String>> {
+        let mut args = Vec::new();
+
+        if let Some(memory) = &self.max_memory {
+            args.push(format!("-Xmx{memory}"));
+        }
+
+        if let Some(proxy) = &self.proxy {
+            let proxy = proxy
+                .trim_start_matches("http://")
+                .trim_start_matches("https://")
+                .trim_end_matches('/');
+
+            let Some((host, port)) = proxy.rsplit_once(':') else {
+                return Err(anyhow!("Proxy must be in the form host:port: {proxy}"));
+            };
+
+            for scheme in ["http", "https"] {
+                args.push(format!("-D{scheme}.proxyHost={host}"));
+                args.push(format!("-D{scheme}.proxyPort={port}"));
+            }
+        }
+
+        if let Some(tmp_dir) = &self.tmp_dir {
+            let tmp_dir = std::path::absolute(tmp_dir)?;
+
+            args.push(format!("-Djava.io.tmpdir={}", tmp_dir.display()));
+        }
+
+        for opens in &self.add_opens {
+            args.push(format!("--add-opens={opens}"));
+        }
+
+        args.extend(self.jvm_args.iter().cloned());
+
+        Ok(args)
+    }
+
+    pub fn env(&self) -> Result<Vec<(String, String)>> {
+        self.env
+            .iter()
+            .map(|it| {
+                it.split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .ok_or(anyhow!("Environment variables must be KEY=VALUE: {it}"))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    pub neo: String,
+    pub side: InstallSide,
+    pub target: PathBuf,
+    pub keep: bool,
+    pub java: JavaOptions,
+    pub processor_mode: ProcessorMode,
+    pub verbose: bool,
+    pub timeout: Option<Duration>,
+    pub argfile: bool,
+    pub jobs: usize,
+    pub sandbox: bool,
+    pub platform: Platform,
+    pub jvm: JvmOptions,
+    pub server: ServerOptions,
+}
+
+/// The directory each side gets installed into. Installing both sides puts them in
+/// `client` and `server` subdirectories of the target.
+pub fn side_targets(side: InstallSide, root: &Path) -> Vec<(Side, PathBuf)> {
+    match side {
+        InstallSide::Both => side
+            .sides()
+            .iter()
+            .map(|it| (*it, root.join(it.get())))
+            .collect(),
+
+        _ => side.sides().iter().map(|it| (*it, root.into())).collect(),
+    }
+}
+
+pub async fn install(opts: &InstallOptions) -> Result<()> {
+    let root = std::path::absolute(make_path_and_create(&opts.target)?)?;
+    let base_path = root.join(".installer");
+    let targets = side_targets(opts.side, &root);
+
+    let result = install_into(opts, &base_path, &targets).await;
+
+    clean_installer_dir(&base_path, opts.keep, result.is_ok())?;
+
+    result
+}
+
+/// The contents of a NeoForge installer JAR.
+#[derive(Debug, Clone)]
+pub struct Installer {
+    pub jar_path: PathBuf,
+    pub profile: NeoProfile,
+    pub version: NeoVersionManifest,
+    /// The raw `version.json`, as written to `versions/<id>/<id>.json` for client installs.
+    pub version_json: String,
+}
+
+/// Downloads the installer JAR into `base_path` (if needed) and reads its install profile
+/// and version JSON.
+pub async fn fetch_installer(neo: &str, base_path: &Path) -> Result<Installer> {
+    let jar_path = base_path.join("installer.jar");
+    let jar_artifact = format!("net.neoforged:neoforge:{neo}:installer");
+    let jar_url = format!("{NEO_MAVEN}/{}", maven_to_path(jar_artifact));
+
+    download_if_needed(&jar_path, jar_url).await?;
+
+    let jar_path = jar_path.canonicalize()?;
+    let jar_file = File::open(&jar_path)?;
+    let mut jar_zip = ZipArchive::new(jar_file)?;
+    let mut profile_entry = jar_zip.by_name("install_profile.json")?;
+    let mut profile_json = String::new();
+
+    profile_entry.read_to_string(&mut profile_json)?;
+
+    drop(profile_entry);
+
+    let mut version_entry = jar_zip.by_name("version.json")?;
+    let mut version_json = String::new();
+
+    version_entry.read_to_string(&mut version_json)?;
+
+    drop(version_entry);
+
+    let mut profile = serde_json::from_str::<NeoProfile>(&profile_json)?;
+    let version = serde_json::from_str::<NeoVersionManifest>(&version_json)?;
+
+    profile.add_minecraft();
+    profile.libraries.extend(version.libraries.iter().cloned());
+
+    Ok(Installer {
+        jar_path,
+        profile,
+        version,
+        version_json,
+    })
+}
+
+/// The libraries a side needs. Client installs also get the vanilla libraries the
+/// NeoForge version inherits, so the game can be launched.
+pub fn side_libraries(
+    installer: &Installer,
+    vanilla: &VersionManifest,
+    side: Side,
+    platform: &Platform,
+) -> Vec<Library> {
+    let mut libraries = installer.profile.libraries.clone();
+
+    if side == Side::Client {
+        let version = installer.version.clone().inherit(vanilla.clone());
+
+        for lib in version.libraries {
+            if !libraries.iter().any(|it| it.name == lib.name) {
+                libraries.push(lib);
+            }
+        }
+    }
+
+    libraries.retain(|it| it.allowed(platform));
+    libraries
+}
+
+/// Extracts the installer's `data/` files into `base_path`.
+fn extract_data(jar_path: &Path, base_path: &Path) -> Result<()> {
+    let data_path = base_path.join("data");
+    let jar_file = File::open(jar_path)?;
+    let mut jar_zip = ZipArchive::new(jar_file)?;
+    let mut data_files = Vec::new();
+
+    for entry in jar_zip.file_names() {
+        if entry.starts_with("data/") && entry != "data/" {
+            data_files.push(entry.to_string());
+        }
+    }
+
+    if !data_files.is_empty() {
+        fs::create_dir_all(data_path)?;
+
+        for file in data_files.iter().progress() {
+            let mut entry = jar_zip.by_name(file)?;
+            let mut content = Vec::new();
+
+            entry.read_to_end(&mut content)?;
+
+            fs::write(base_path.join(file), content)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a processor runs on the given side.
+pub fn runs_on(proc: &Processor, side: Side) -> bool {
+    proc.sides.as_ref().is_none_or(|it| it.contains(&side))
+}
+
+/// The context processors for one side run in. `base_path` is the installer directory
+/// shared by every side, which has the installer's data.
+pub fn processor_context(
+    opts: &InstallOptions,
+    work_dir: &Path,
+    base_path: &Path,
+    vars: HashMap<String, String>,
+    java: String,
+) -> Result<ProcessorContext> {
+    // When installing both sides, each one keeps its logs, argfiles and temp files in its
+    // own directory inside the shared one.
+    let side_dir = match base_path
+        .parent()
+        .and_then(|root| work_dir.strip_prefix(root).ok())
+    {
+        Some(side) => base_path.join(side),
+        None => base_path.to_path_buf(),
+    };
+
+    let mut jvm = opts.jvm.clone();
+
+    // Sandboxed processors can't write to the system temp directory.
+    if opts.sandbox && jvm.tmp_dir.is_none() {
+        jvm.tmp_dir = Some(side_dir.join("tmp"));
+    }
+
+    let mut jvm_args = jvm.args()?;
+
+    if opts.sandbox {
+        jvm_args.push("-XX:-UsePerfData".into());
+    }
+
+    Ok(ProcessorContext {
+        vars,
+        lib_path: work_dir.join("libraries"),
+        work_dir: work_dir.to_path_buf(),
+        installer_dir: base_path.to_path_buf(),
+        log_dir: side_dir.join("logs"),
+        args_dir: side_dir.join("args"),
+        java,
+        jvm_args,
+        env: opts.jvm.env()?,
+        mode: opts.processor_mode,
+        verbose: opts.verbose,
+        timeout: opts.timeout,
+        argfile: opts.argfile,
+        sandbox: opts.sandbox,
+    })
+}
+
+/// Whether any of these processors need Java. Installs that only run native processors
+/// don't, so they work on hosts without it.
+pub fn needs_java<'a>(
+    opts: &InstallOptions,
+    mut processors: impl Iterator<Item = &'a Processor>,
+) -> bool {
+    processors.any(|proc| opts.processor_mode == ProcessorMode::Java || find_native(proc).is_none())
+}
+
+/// Installs each side into its target directory. The installer is extracted once into
+/// `base_path`, and libraries already downloaded for one side are linked into the next.
+async fn install_into(
+    opts: &InstallOptions,
+    base_path: &Path,
+    targets: &[(Side, PathBuf)],
+) -> Result<()> {
+    let installer = fetch_installer(&opts.neo, base_path).await?;
+    let Installer {
+        jar_path, profile, ..
+    } = &installer;
+
+    extract_data(jar_path, base_path)?;
+
+    let vanilla = fetch_version_manifest(&profile.minecraft).await?;
+    let mut installs = Vec::new();
+
+    for (side, work_dir) in targets {
+        let side = *side;
+        let lib_path = make_path_and_create(work_dir.join("libraries"))?;
+        let vars = setup_vars(profile, side, &lib_path, base_path, jar_path)?;
+
+        let processors = profile
+            .processors
+            .iter()
+            .enumerate()
+            .filter(|(_, proc)| {
+                let runs = runs_on(proc, side);
+
+                if !runs {
+                    eprintln!(
+                        "Processor skipped due to being on the wrong side: {}",
+                        proc.jar
+                    );
+                }
+
+                runs
+            })
+            .map(|(index, proc)| (index, proc.clone()))
+            .collect::<Vec<_>>();
+
+        let libraries = side_libraries(&installer, &vanilla, side, &opts.platform);
+
+        installs.push((side, work_dir, lib_path, vars, processors, libraries));
+    }
+
+    let processors = installs.iter().flat_map(|it| &it.4).map(|(_, proc)| proc);
+
+    if opts.processor_mode == ProcessorMode::Native {
+        let missing = processors
+            .clone()
+            .filter(|it| find_native(it).is_none())
+            .map(|it| match it.task() {
+                Some(task) => format!("{} ({task})", it.artifact()),
+                None => it.artifact().to_string(),
+            })
+            .unique()
+            .collect_vec();
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "These processors have no native implementation, use --processors auto to run them with Java: {}",
+                missing.join(", ")
+            ));
+        }
+    }
+
+    let java = match needs_java(opts, processors) {
+        true => opts.java.resolve(vanilla.java_version.as_ref()).await?,
+        false => opts.java.java.clone().unwrap_or(JAVA_EXE.into()),
+    };
+
+    let mut downloaded = None::<&Path>;
+
+    for (side, work_dir, lib_path, vars, _, libraries) in &installs {
+        if let Some(from) = downloaded {
+            link_libs(libraries, &opts.platform, from, lib_path)?;
+        }
+
+        download_libs(profile, libraries, &opts.platform, lib_path).await?;
+        downloaded = Some(lib_path);
+
+        check_interrupted()?;
+        download_mc_jars(
+            profile, &vanilla, vars, *side, work_dir, lib_path, base_path,
+        )
+        .await?;
+
+        if *side == Side::Client
+            && let Some(asset_index) = &vanilla.asset_index
+        {
+            check_interrupted()?;
+            download_assets(work_dir, asset_index)?;
+        }
+
+        if *side == Side::Client
+            && let Some(config) = vanilla.logging.as_ref().and_then(|it| it.client.as_ref())
+        {
+            download_log_config(work_dir, config)?;
+        }
+    }
+
+    let mut groups = Vec::new();
+
+    for (_, work_dir, _, vars, processors, _) in &mut installs {
+        let ctx = processor_context(opts, work_dir, base_path, mem::take(vars), java.clone())?;
+
+        groups.push((mem::take(processors), ctx));
+    }
+
+    // Processors like EXTRACT_FILES write the installer's own start scripts, which would
+    // replace the ones from earlier installs.
+    let saved = match targets.iter().find(|(side, _)| *side == Side::Server) {
+        Some((_, work_dir)) => save_scripts(work_dir)?,
+        None => Vec::new(),
+    };
+
+    // Both sides' processors share one graph, so they run alongside each other.
+    let result = run_processors(groups, opts.jobs).await;
+
+    restore_scripts(&saved)?;
+    result?;
+
+    for (side, work_dir, lib_path, _, _, libraries) in installs {
+        if side == Side::Client {
+            let client_jar = profile.data("MINECRAFT_JAR", side, &lib_path, base_path)?;
+
+            install_client(work_dir, &installer, Path::new(&client_jar)).await?;
+
+            extract_natives(
+                &libraries,
+                &opts.platform,
+                &lib_path,
+                &natives_dir(work_dir, &installer.version.id),
+            )?;
+        }
+
+        if side == Side::Server {
+            write_server_files(work_dir, &opts.neo, base_path, &opts.server)?;
+            write_server_config(work_dir, &opts.server)?;
+        }
+    }
+
+    Ok(())
+}
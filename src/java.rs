@@ -0,0 +1,325 @@
+use crate::{
+    manifest::JavaVersion,
+    rules::Platform,
+    runtime::{JAVA_RUNTIME_URL, default_runtimes_dir, install_runtime},
+    util::find_executable,
+};
+use anyhow::{Context, Result};
+use clap::Args;
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[cfg(not(windows))]
+pub const JAVA_EXE: &str = "java";
+#[cfg(windows)]
+pub const JAVA_EXE: &str = "java.exe";
+
+/// How to pick the Java runtime to use.
+#[derive(Debug, Clone, Default, Args, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct JavaOptions {
+    /// The path to the Java executable to use. Picked from the installed runtimes if not
+    /// given.
+    #[clap(short = 'j', long = "java")]
+    pub java: Option<String>,
+
+    /// Download the Java runtime Mojang ships for the Minecraft version if no suitable
+    /// one is installed. `--download-java=false` turns it off when the config enables it.
+    #[clap(
+        long = "download-java",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub download_java: Option<bool>,
+
+    /// The URL of Mojang's Java runtime index, or a stand-in for it.
+    #[clap(long = "java-runtime-url", value_name = "URL")]
+    pub runtime_url: Option<String>,
+
+    /// Where downloaded Java runtimes are kept, shared between installs.
+    #[clap(long = "runtimes-dir", value_name = "DIR")]
+    pub runtimes_dir: Option<PathBuf>,
+}
+
+impl JavaOptions {
+    /// Merges two sets of options, with `other` taking priority.
+    pub fn merge(self, other: JavaOptions) -> Self {
+        Self {
+            java: other.java.or(self.java),
+            download_java: other.download_java.or(self.download_java),
+            runtime_url: other.runtime_url.or(self.runtime_url),
+            runtimes_dir: other.runtimes_dir.or(self.runtimes_dir),
+        }
+    }
+
+    /// Picks the Java executable for a Minecraft version's Java requirement, downloading
+    /// Mojang's runtime for it if that's enabled and nothing installed is suitable.
+    pub async fn resolve(&self, required: Option<&JavaVersion>) -> Result<String> {
+        let err = match select_java(self.java.as_deref(), required.map(|it| it.major_version)) {
+            Ok(java) => return Ok(java.command()),
+            Err(err) => err,
+        };
+
+        let Some(required) =
+            required.filter(|_| self.download_java == Some(true) && self.java.is_none())
+        else {
+            return Err(err);
+        };
+
+        let runtimes_dir = match &self.runtimes_dir {
+            Some(dir) => std::path::absolute(dir)?,
+            None => default_runtimes_dir()?,
+        };
+
+        let java = install_runtime(
+            &required.component,
+            self.runtime_url.as_deref().unwrap_or(JAVA_RUNTIME_URL),
+            &runtimes_dir,
+            &Platform::current(),
+        )
+        .await?;
+
+        println!("Using Java {} at {}.", java.version, java.path.display());
+
+        Ok(java.command())
+    }
+}
+
+/// A Java runtime and its version.
+#[derive(Debug, Clone)]
+pub struct JavaRuntime {
+    pub path: PathBuf,
+    pub version: String,
+    pub major: u32,
+}
+
+impl JavaRuntime {
+    /// The runtime's executable, as passed to `Command`.
+    pub fn command(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+/// Reads the major version from a Java version string, like `17.0.15` or `1.8.0_392`.
+pub fn major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Finds the version in the output of `java -version`, which is quoted on a line like
+/// `openjdk version "21.0.5" 2024-10-15`.
+fn version_in(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.split_once(" version \""))
+        .and_then(|(_, rest)| rest.split('"').next())
+        .map(String::from)
+}
+
+/// The home directory of a Java executable, following links like `/usr/bin/java`.
+fn java_home(java: &Path) -> Option<PathBuf> {
+    Some(java.canonicalize().ok()?.parent()?.parent()?.to_path_buf())
+}
+
+/// Reads a Java runtime's version from the `release` file in its home directory, or by
+/// running `java -version` if it doesn't have one.
+pub fn probe(java: &Path) -> Result<JavaRuntime> {
+    let release = java_home(java)
+        .and_then(|home| fs::read_to_string(home.join("release")).ok())
+        .and_then(|release| {
+            release
+                .lines()
+                .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+                .map(|it| it.trim().trim_matches('"').to_string())
+        });
+
+    let version = match release {
+        Some(version) => version,
+
+        None => {
+            let output = Command::new(java)
+                .arg("-version")
+                .output()
+                .with_context(|| format!("Failed to run {}", java.display()))?;
+
+            version_in(&String::from_utf8_lossy(&output.stderr))
+                .ok_or(anyhow!("Couldn't read the version of {}", java.display()))?
+        }
+    };
+
+    let major = major_version(&version).ok_or(anyhow!(
+        "Unknown Java version for {}: {version}",
+        java.display()
+    ))?;
+
+    Ok(JavaRuntime {
+        path: java.to_path_buf(),
+        version,
+        major,
+    })
+}
+
+/// Directories that Java runtimes are commonly installed into, one per subdirectory.
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = [
+        "/usr/lib/jvm",
+        "/usr/lib64/jvm",
+        "/usr/java",
+        "/opt/java",
+        "/Library/Java/JavaVirtualMachines",
+    ]
+    .map(PathBuf::from)
+    .to_vec();
+
+    if let Some(sdkman) = env::var_os("SDKMAN_DIR") {
+        dirs.push(PathBuf::from(sdkman).join("candidates").join("java"));
+    }
+
+    if let Some(home) = env::var_os("HOME") {
+        let home = PathBuf::from(home);
+
+        dirs.push(home.join(".sdkman").join("candidates").join("java"));
+        dirs.push(
+            home.join("Library")
+                .join("Java")
+                .join("JavaVirtualMachines"),
+        );
+    }
+
+    if let Some(program_files) = env::var_os("ProgramFiles") {
+        let program_files = PathBuf::from(program_files);
+
+        for vendor in ["Java", "Eclipse Adoptium", "Microsoft", "Zulu"] {
+            dirs.push(program_files.join(vendor));
+        }
+    }
+
+    dirs
+}
+
+/// Finds the Java runtimes on this machine: `JAVA_HOME`, the `PATH`, and the usual
+/// install locations, including SDKMAN's.
+pub fn discover() -> Vec<JavaRuntime> {
+    let mut candidates = Vec::new();
+
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        candidates.push(PathBuf::from(home).join("bin").join(JAVA_EXE));
+    }
+
+    candidates.extend(find_executable("java"));
+
+    for dir in search_dirs() {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let home = entry.path();
+
+            candidates.push(home.join("bin").join(JAVA_EXE));
+            candidates.push(
+                home.join("Contents")
+                    .join("Home")
+                    .join("bin")
+                    .join(JAVA_EXE),
+            );
+        }
+    }
+
+    let mut seen = HashSet::new();
+
+    candidates
+        .into_iter()
+        .filter(|it| it.is_file())
+        .filter(|it| seen.insert(it.canonicalize().unwrap_or(it.clone())))
+        .filter_map(|it| probe(&it).ok())
+        .collect()
+}
+
+/// Picks the Java runtime to use. A requested runtime is only checked against `required`,
+/// otherwise the installed runtime closest to it is picked, or the newest one if nothing
+/// in particular is required.
+pub fn select_java(requested: Option<&str>, required: Option<u32>) -> Result<JavaRuntime> {
+    if let Some(requested) = requested {
+        // Servers run from their own directory, so a relative path would break there.
+        let path = std::path::absolute(
+            find_executable(requested).ok_or(anyhow!("Java executable not found: {requested}"))?,
+        )?;
+
+        let java = probe(&path)?;
+
+        return match required {
+            Some(required) if java.major < required => Err(anyhow!(
+                "{} is Java {}, but this version of Minecraft needs Java {required} or newer!",
+                path.display(),
+                java.major
+            )),
+
+            _ => Ok(java),
+        };
+    }
+
+    let found = discover();
+
+    let java = found
+        .iter()
+        .filter(|it| required.is_none_or(|required| it.major >= required))
+        .min_by_key(|it| match required {
+            Some(_) => it.major,
+            None => u32::MAX - it.major,
+        })
+        .cloned();
+
+    let Some(java) = java else {
+        let found = found
+            .iter()
+            .map(|it| format!("Java {} at {}", it.major, it.path.display()))
+            .collect::<Vec<_>>();
+
+        return Err(anyhow!(
+            "No Java {}runtime was found (found: {}). Install one or pass its path with --java!",
+            required.map(|it| format!("{it}+ ")).unwrap_or_default(),
+            match found.is_empty() {
+                true => "none".into(),
+                false => found.join(", "),
+            }
+        ));
+    };
+
+    println!("Using Java {} at {}.", java.version, java.path.display());
+
+    Ok(java)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn major_versions() {
+        assert_eq!(major_version("1.8.0_392"), Some(8));
+        assert_eq!(major_version("17.0.15"), Some(17));
+        assert_eq!(major_version("21"), Some(21));
+        assert_eq!(major_version("24-ea"), Some(24));
+        assert_eq!(major_version("unknown"), None);
+    }
+
+    #[test]
+    fn version_output() {
+        let java8 = "openjdk version \"1.8.0_392\"\nOpenJDK Runtime Environment (Temurin)(build 1.8.0_392-b08)\nOpenJDK 64-Bit Server VM (Temurin)(build 25.392-b08, mixed mode)\n";
+        let java21 = "Picked up JAVA_TOOL_OPTIONS: -Dfile.encoding=UTF-8\njava version \"21.0.5\" 2024-10-15 LTS\nJava(TM) SE Runtime Environment (build 21.0.5+9-LTS-239)\nJava HotSpot(TM) 64-Bit Server VM (build 21.0.5+9-LTS-239, mixed mode, sharing)\n";
+
+        assert_eq!(version_in(java8).as_deref(), Some("1.8.0_392"));
+        assert_eq!(version_in(java21).as_deref(), Some("21.0.5"));
+        assert_eq!(
+            version_in("Error: Could not create the Java Virtual Machine.\n"),
+            None
+        );
+    }
+}
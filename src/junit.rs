@@ -0,0 +1,93 @@
+use anyhow::Result;
+use std::{fmt::Write, fs, path::Path};
+
+/// A JUnit XML test suite, as read by most CI systems.
+#[derive(Debug, Clone, Default)]
+pub struct TestSuite {
+    pub name: String,
+    pub cases: Vec<TestCase>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TestCase {
+    pub class: String,
+    pub name: String,
+    /// How long the test took, in seconds.
+    pub time: f64,
+    /// Why the test failed, if it did.
+    pub failure: Option<String>,
+    /// Why the test was skipped, if it was.
+    pub skipped: Option<String>,
+    /// Extra details shown with the result, like a crash report path.
+    pub output: Option<String>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl TestSuite {
+    pub fn to_xml(&self) -> String {
+        let failures = self.cases.iter().filter(|it| it.failure.is_some()).count();
+        let skipped = self.cases.iter().filter(|it| it.skipped.is_some()).count();
+        let time = self.cases.iter().map(|it| it.time).sum::<f64>();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" errors=\"0\" skipped=\"{skipped}\" time=\"{time:.3}\">",
+            escape(&self.name),
+            self.cases.len(),
+        );
+
+        for case in &self.cases {
+            let _ = write!(
+                xml,
+                "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\">",
+                escape(&case.class),
+                escape(&case.name),
+                case.time,
+            );
+
+            if let Some(failure) = &case.failure {
+                let _ = write!(
+                    xml,
+                    "\n    <failure message=\"{}\">{}</failure>",
+                    escape(failure),
+                    escape(failure)
+                );
+            }
+
+            if let Some(skipped) = &case.skipped {
+                let _ = write!(xml, "\n    <skipped message=\"{}\"/>", escape(skipped));
+            }
+
+            if let Some(output) = &case.output {
+                let _ = write!(xml, "\n    <system-out>{}</system-out>", escape(output));
+            }
+
+            if case.failure.is_some() || case.skipped.is_some() || case.output.is_some() {
+                xml.push_str("\n  ");
+            }
+
+            xml.push_str("</testcase>\n");
+        }
+
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, self.to_xml())?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{
+    assets::{download_assets, download_log_config},
+    download::{Download, download_all},
+    install::JvmOptions,
+    java::JavaOptions,
+    launcher::natives_dir,
+    libraries::extract_natives,
+    manifest::{Argument, VersionManifest, fetch_version_json},
+    profile::NeoVersionManifest,
+    rules::{Features, Platform},
+    util::jvm_tmp_dir,
+};
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use md5::{Digest, Md5};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+pub const LAUNCHER_NAME: &str = env!("CARGO_PKG_NAME");
+pub const LAUNCHER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[cfg(not(windows))]
+pub const CLASSPATH_SEPARATOR: &str = ":";
+#[cfg(windows)]
+pub const CLASSPATH_SEPARATOR: &str = ";";
+
+#[derive(Debug, Clone)]
+pub struct LaunchOptions {
+    pub neo: String,
+    pub game_dir: PathBuf,
+    pub username: String,
+    pub java: JavaOptions,
+    pub demo: bool,
+    /// The window size, as `(width, height)`.
+    pub resolution: Option<(u32, u32)>,
+    pub jvm: JvmOptions,
+}
+
+/// The UUID offline-mode servers give a player: a version 3 UUID of `OfflinePlayer:<name>`,
+/// like Java's `UUID.nameUUIDFromBytes`.
+pub fn offline_uuid(username: &str) -> String {
+    let mut hash = Md5::digest(format!("OfflinePlayer:{username}"));
+
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+
+    hash.iter().map(|it| format!("{it:02x}")).collect()
+}
+
+/// Replaces `${key}` placeholders in a launch argument. Unknown keys are left as-is.
+pub fn substitute(value: &str, vars: &HashMap<&str, String>) -> String {
+    let mut out = value.to_string();
+
+    for (key, replacement) in vars {
+        out = out.replace(&format!("${{{key}}}"), replacement);
+    }
+
+    out
+}
+
+/// Reads an installed version JSON from `versions/<id>/<id>.json`.
+pub fn read_version(game_dir: &Path, id: &str) -> Result<String> {
+    let path = game_dir
+        .join("versions")
+        .join(id)
+        .join(format!("{id}.json"));
+
+    fs::read_to_string(&path).with_context(|| {
+        format!(
+            "Failed to read {} (is the client installed?)",
+            path.display()
+        )
+    })
+}
+
+/// Finds the installed version JSON for a NeoForge version, by the `--fml.neoForgeVersion`
+/// in its game arguments. Returns its id and contents.
+fn find_neo_version(game_dir: &Path, neo: &str) -> Result<(String, NeoVersionManifest)> {
+    let is_neo = |version: &NeoVersionManifest| {
+        version
+            .arguments
+            .game
+            .iter()
+            .tuple_windows()
+            .any(|pair| match pair {
+                (Argument::Plain(flag), Argument::Plain(value)) => {
+                    flag == "--fml.neoForgeVersion" && value == neo
+                }
+
+                _ => false,
+            })
+    };
+
+    fs::read_dir(game_dir.join("versions"))
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.ok())
+        .filter_map(|it| {
+            let id = it.file_name().to_string_lossy().into_owned();
+            let json = read_version(game_dir, &id).ok()?;
+
+            Some((id, serde_json::from_str::<NeoVersionManifest>(&json).ok()?))
+        })
+        .find(|(_, version)| is_neo(version))
+        .ok_or(anyhow!(
+            "NeoForge {neo} isn't installed in {} (is the client installed?)",
+            game_dir.display()
+        ))
+}
+
+/// Reads the vanilla version JSON from the game directory, fetching it if it's missing.
+async fn vanilla_version(game_dir: &Path, id: &str) -> Result<VersionManifest> {
+    match read_version(game_dir, id) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+
+        Err(_) => {
+            let json = fetch_version_json(id).await?;
+            let dir = game_dir.join("versions").join(id);
+
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(format!("{id}.json")), &json)?;
+
+            Ok(serde_json::from_str(&json)?)
+        }
+    }
+}
+
+/// Makes sure everything an installed client needs is present, then starts it with an
+/// offline account.
+pub async fn launch(opts: &LaunchOptions) -> Result<()> {
+    let game_dir = std::path::absolute(&opts.game_dir)?;
+    let lib_path = game_dir.join("libraries");
+    let platform = Platform::current();
+    let (id, neo) = find_neo_version(&game_dir, &opts.neo)?;
+    let inherits_from = neo
+        .inherits_from
+        .clone()
+        .ok_or(anyhow!("{id} doesn't inherit from a Minecraft version!"))?;
+
+    let vanilla = vanilla_version(&game_dir, &inherits_from).await?;
+    let client_jar = game_dir
+        .join("versions")
+        .join(&vanilla.id)
+        .join(format!("{}.jar", vanilla.id));
+
+    let java = opts.java.resolve(vanilla.java_version.as_ref()).await?;
+    let version = neo.inherit(vanilla.clone());
+    let natives = natives_dir(&game_dir, &id);
+
+    let libraries = version
+        .libraries
+        .iter()
+        .filter(|it| it.allowed(&platform))
+        .cloned()
+        .collect_vec();
+
+    let mut downloads = libraries
+        .iter()
+        .flat_map(|it| it.downloads(&platform, &lib_path))
+        .collect_vec();
+
+    downloads.push(Download {
+        path: client_jar.clone(),
+        urls: vec![vanilla.downloads.client.url.clone()],
+        sha1: Some(vanilla.downloads.client.sha1.clone()),
+    });
+
+    let failed = download_all(&downloads)?;
+
+    if !failed.is_empty() {
+        for (_, err) in &failed {
+            eprintln!("{err}");
+        }
+
+        return Err(anyhow!("Failed to download {} libraries!", failed.len()));
+    }
+
+    extract_natives(&libraries, &platform, &lib_path, &natives)?;
+
+    if let Some(asset_index) = &vanilla.asset_index {
+        download_assets(&game_dir, asset_index)?;
+    }
+
+    let log_config = match vanilla.logging.as_ref().and_then(|it| it.client.as_ref()) {
+        Some(config) => Some(download_log_config(&game_dir, config)?),
+        None => None,
+    };
+
+    let classpath = libraries
+        .iter()
+        .filter_map(|it| it.artifact_path())
+        .map(|it| lib_path.join(it))
+        .chain([client_jar])
+        .map(|it| it.to_str().unwrap().to_string())
+        .unique()
+        .join(CLASSPATH_SEPARATOR);
+
+    let (width, height) = opts.resolution.unwrap_or_default();
+
+    let features = Features {
+        is_demo_user: opts.demo,
+        has_custom_resolution: opts.resolution.is_some(),
+        ..Default::default()
+    };
+
+    let vars = HashMap::from([
+        ("auth_player_name", opts.username.clone()),
+        ("auth_uuid", offline_uuid(&opts.username)),
+        ("auth_access_token", "0".into()),
+        ("auth_xuid", "0".into()),
+        ("clientid", "0".into()),
+        ("user_type", "legacy".into()),
+        ("user_properties", "{}".into()),
+        ("version_name", id.clone()),
+        ("version_type", version.kind.clone()),
+        ("game_directory", game_dir.to_str().unwrap().into()),
+        (
+            "assets_root",
+            game_dir.join("assets").to_str().unwrap().into(),
+        ),
+        (
+            "assets_index_name",
+            vanilla
+                .asset_index
+                .as_ref()
+                .map(|it| it.id.clone())
+                .unwrap_or_default(),
+        ),
+        ("natives_directory", natives.to_str().unwrap().into()),
+        ("library_directory", lib_path.to_str().unwrap().into()),
+        ("launcher_name", LAUNCHER_NAME.into()),
+        ("launcher_version", LAUNCHER_VERSION.into()),
+        ("classpath_separator", CLASSPATH_SEPARATOR.into()),
+        ("resolution_width", width.to_string()),
+        ("resolution_height", height.to_string()),
+        ("classpath", classpath),
+    ]);
+
+    let expand = |args: &[Argument]| {
+        args.iter()
+            .flat_map(|it| it.values(&platform, &features))
+            .map(|it| substitute(&it, &vars))
+            .collect_vec()
+    };
+
+    let jvm_args = opts.jvm.args()?;
+
+    if let Some(tmp_dir) = jvm_tmp_dir(&jvm_args) {
+        fs::create_dir_all(tmp_dir)?;
+    }
+
+    let mut cmd = Command::new(&java);
+
+    cmd.args(jvm_args)
+        .args(expand(&version.arguments.jvm))
+        .args(log_config)
+        .arg(&version.main_class)
+        .args(expand(&version.arguments.game))
+        .envs(opts.jvm.env()?)
+        .current_dir(&game_dir);
+
+    println!("Launching {id} as {}...", opts.username);
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to start Java: {java}"))?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("Minecraft exited with {status}")),
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{install::Installer, manifest::fetch_version_json};
+use anyhow::Result;
+use serde_json::{Map, Value, json};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const LAUNCHER_PROFILES: &str = "launcher_profiles.json";
+
+/// Where the natives of a version are extracted to.
+pub fn natives_dir(game_dir: &Path, version_id: &str) -> PathBuf {
+    game_dir.join("versions").join(version_id).join("natives")
+}
+
+/// Makes a client install visible to the official launcher: writes the NeoForge and vanilla
+/// versions into `versions/` and adds a profile to `launcher_profiles.json`.
+pub async fn install_client(
+    game_dir: &Path,
+    installer: &Installer,
+    client_jar: &Path,
+) -> Result<()> {
+    let minecraft = &installer.profile.minecraft;
+    let version_id = &installer.version.id;
+    let vanilla_dir = game_dir.join("versions").join(minecraft);
+    let vanilla_json = vanilla_dir.join(format!("{minecraft}.json"));
+    let vanilla_jar = vanilla_dir.join(format!("{minecraft}.jar"));
+    let version_dir = game_dir.join("versions").join(version_id);
+
+    fs::create_dir_all(&vanilla_dir)?;
+    fs::create_dir_all(&version_dir)?;
+
+    if !vanilla_json.exists() {
+        fs::write(vanilla_json, fetch_version_json(minecraft).await?)?;
+    }
+
+    if !vanilla_jar.exists() && fs::hard_link(client_jar, &vanilla_jar).is_err() {
+        fs::copy(client_jar, &vanilla_jar)?;
+    }
+
+    fs::write(
+        version_dir.join(format!("{version_id}.json")),
+        &installer.version_json,
+    )?;
+
+    add_launcher_profile(game_dir, installer)
+}
+
+/// Adds (or updates) the installer's profile in `launcher_profiles.json`, keeping
+/// everything else in the file as-is.
+pub fn add_launcher_profile(game_dir: &Path, installer: &Installer) -> Result<()> {
+    let path = game_dir.join(LAUNCHER_PROFILES);
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+    let mut json = match path.exists() {
+        true => serde_json::from_str::<Value>(&fs::read_to_string(&path)?)?,
+        false => json!({}),
+    };
+
+    let root = json
+        .as_object_mut()
+        .ok_or(anyhow!("Invalid launcher profiles: {}", path.display()))?;
+
+    let profiles = root
+        .entry("profiles")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or(anyhow!("Invalid launcher profiles: {}", path.display()))?;
+
+    let name = &installer.profile.profile;
+
+    let profile = profiles
+        .entry(name.clone())
+        .or_insert_with(|| json!({ "created": now }))
+        .as_object_mut()
+        .ok_or(anyhow!("Invalid launcher profile: {name}"))?;
+
+    profile.insert("name".into(), name.clone().into());
+    profile.insert("type".into(), "custom".into());
+    profile.insert("lastUsed".into(), now.into());
+    profile.insert("lastVersionId".into(), installer.version.id.clone().into());
+    profile.insert("icon".into(), installer.profile.icon.clone().into());
+
+    fs::write(path, serde_json::to_string_pretty(&json)?)?;
+
+    Ok(())
+}
@@ -4,14 +4,34 @@ extern crate anyhow;
 #[macro_use]
 extern crate serde;
 
+pub mod argfile;
+pub mod assets;
+pub mod bundler;
 pub mod cli;
+pub mod config;
 pub mod download;
+pub mod graph;
+pub mod install;
+pub mod java;
+pub mod junit;
+pub mod launch;
+pub mod launcher;
 pub mod libraries;
 pub mod manifest;
 pub mod maven;
 pub mod meta;
 pub mod mirrors;
+pub mod native;
+pub mod placeholders;
+pub mod plan;
 pub mod processors;
 pub mod profile;
+pub mod properties;
+pub mod rules;
+pub mod runtime;
+pub mod sandbox;
+pub mod server;
 pub mod side;
+pub mod signal;
+pub mod smoke;
 pub mod util;
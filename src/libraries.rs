@@ -1,22 +1,155 @@
-use crate::{maven::maven_to_path, mirrors::Mirror, profile::NeoProfile};
+use crate::{
+    download::{Download, download_all},
+    maven::maven_to_path,
+    mirrors::Mirror,
+    profile::NeoProfile,
+    rules::{Features, Platform, Rule, rules_allow},
+};
 use anyhow::Result;
-use indicatif::{ParallelProgressIterator, ProgressStyle};
 use itertools::Itertools;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use reqwest::StatusCode;
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+use zip::ZipArchive;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Library {
     pub name: String,
+    #[serde(default)]
     pub downloads: LibraryDownloads,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Maps an OS name to the classifier of this library's natives for it.
+    pub natives: Option<HashMap<String, String>>,
+    pub extract: Option<LibraryExtract>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Library {
+    /// The library's `group:artifact[:classifier]`, which identifies it across versions.
+    pub fn key(&self) -> String {
+        let mut parts = self.name.split('@').next().unwrap_or_default().split(':');
+        let group = parts.next().unwrap_or_default();
+        let artifact = parts.next().unwrap_or_default();
+
+        match parts.nth(1) {
+            Some(classifier) => format!("{group}:{artifact}:{classifier}"),
+            None => format!("{group}:{artifact}"),
+        }
+    }
+
+    pub fn allowed(&self, platform: &Platform) -> bool {
+        rules_allow(&self.rules, platform, &Features::default())
+    }
+
+    /// The classifier of this library's natives for a platform, if it has any.
+    pub fn natives_classifier(&self, platform: &Platform) -> Option<String> {
+        self.natives
+            .as_ref()?
+            .get(&platform.os)
+            .map(|it| it.replace("${arch}", platform.bits()))
+    }
+
+    /// The SHA-1 this library's JSON lists for one of its files, by maven coordinate.
+    pub fn sha1(&self, coord: &str) -> Option<String> {
+        let info = match coord
+            .strip_prefix(self.name.as_str())
+            .and_then(|it| it.strip_prefix(':'))
+        {
+            Some(classifier) => self.downloads.classifiers.as_ref()?.get(classifier)?,
+            None => self.downloads.artifact.as_ref()?,
+        };
+
+        Some(info.sha1.clone()).filter(|it| !it.is_empty())
+    }
+
+    /// The maven coordinates of every file this library needs on a platform: the main
+    /// artifact (unless the library is only natives) and the natives JAR.
+    pub fn coords(&self, platform: &Platform) -> Vec<String> {
+        let mut coords = Vec::new();
+
+        if self.downloads.artifact.is_some() || self.natives.is_none() {
+            coords.push(self.name.clone());
+        }
+
+        if let Some(classifier) = self.natives_classifier(platform) {
+            coords.push(format!("{}:{classifier}", self.name));
+        }
+
+        coords
+    }
+
+    /// The path of the library's main JAR relative to the libraries directory, if it has
+    /// one that belongs on the classpath.
+    pub fn artifact_path(&self) -> Option<String> {
+        match &self.downloads.artifact {
+            Some(artifact) => Some(artifact.path.clone()),
+            None if self.natives.is_none() => Some(maven_to_path(&self.name)),
+            None => None,
+        }
+    }
+
+    /// The files this library needs on a platform, using the download URLs in its JSON.
+    /// Files without a URL are generated by the installer, so have nowhere to come from.
+    pub fn downloads(&self, platform: &Platform, lib_path: &Path) -> Vec<Download> {
+        let download = |info: &LibraryDownload| Download {
+            path: lib_path.join(&info.path),
+            urls: Some(info.url.clone())
+                .filter(|it| !it.is_empty())
+                .into_iter()
+                .collect(),
+            sha1: Some(info.sha1.clone()).filter(|it| !it.is_empty()),
+        };
+
+        let mut downloads = Vec::new();
+
+        match &self.downloads.artifact {
+            Some(artifact) => downloads.push(download(artifact)),
+
+            None if self.natives.is_none() => {
+                let base_path = maven_to_path(&self.name);
+
+                downloads.push(Download {
+                    path: lib_path.join(&base_path),
+                    urls: vec![format!("{MOJANG_LIBRARIES_URL}/{base_path}")],
+                    sha1: None,
+                });
+            }
+
+            None => {}
+        }
+
+        let classifier = self.natives_classifier(platform).and_then(|it| {
+            self.downloads
+                .classifiers
+                .as_ref()
+                .and_then(|classifiers| classifiers.get(&it))
+        });
+
+        if let Some(classifier) = classifier {
+            downloads.push(download(classifier));
+        }
+
+        downloads
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LibraryDownloads {
-    pub artifact: LibraryDownload,
+    pub artifact: Option<LibraryDownload>,
+    pub classifiers: Option<HashMap<String, LibraryDownload>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryExtract {
+    /// Path prefixes to skip when extracting natives.
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,57 +161,119 @@ pub struct LibraryDownload {
     pub path: String,
 }
 
-pub async fn download_libs(data: &NeoProfile, lib_path: &PathBuf) -> Result<()> {
+pub const MOJANG_LIBRARIES_URL: &str = "https://libraries.minecraft.net";
+
+/// Fetches the profile's mirror list and returns the base URL of the first mirror.
+pub async fn fetch_mirror(data: &NeoProfile) -> Result<String> {
     let mirrors = reqwest::get(&data.mirror_list)
         .await?
         .json::<Vec<Mirror>>()
         .await?;
 
-    let mirror = mirrors.first().unwrap();
-    let base_url = mirror.url.clone();
-    let mc_libs_url = "https://libraries.minecraft.net";
+    mirrors
+        .into_iter()
+        .next()
+        .map(|it| it.url)
+        .ok_or(anyhow!("Mirror list is empty: {}", data.mirror_list))
+}
+
+pub async fn download_libs(
+    data: &NeoProfile,
+    libraries: &[Library],
+    platform: &Platform,
+    lib_path: &Path,
+) -> Result<()> {
+    let base_url = fetch_mirror(data).await?;
 
-    data.libraries
+    let downloads = libraries
         .iter()
-        .map(|v| v.name.clone())
-        .collect_vec()
-        .par_iter()
-        .progress_with_style(
-            ProgressStyle::default_bar()
-                .progress_chars("=> ")
-                .template("{msg} [{wide_bar:.cyan/blue}] {percent}% {pos:>7}/{len:7}")?,
-        )
-        .map(|lib| -> Result<()> {
-            let base_path = maven_to_path(lib);
-            let file_path = lib_path.join(&base_path);
-
-            if fs::exists(&file_path)? {
-                return Ok(());
+        .filter(|v| v.allowed(platform))
+        .flat_map(|lib| {
+            lib.coords(platform)
+                .into_iter()
+                .map(move |coord| (lib.sha1(&coord), coord))
+        })
+        .map(|(sha1, coord)| {
+            let base_path = maven_to_path(coord);
+
+            Download {
+                path: lib_path.join(&base_path),
+                urls: vec![
+                    format!("{base_url}/{base_path}"),
+                    format!("{MOJANG_LIBRARIES_URL}/{base_path}"),
+                ],
+                sha1,
             }
+        })
+        .collect_vec();
+
+    for (_, err) in download_all(&downloads)? {
+        eprintln!("An error occured downloading a library: {err}");
+    }
+
+    Ok(())
+}
+
+/// Hard links (or copies, if that fails) already downloaded libraries from one library
+/// directory into another.
+pub fn link_libs(libraries: &[Library], platform: &Platform, from: &Path, to: &Path) -> Result<()> {
+    for coord in libraries.iter().flat_map(|it| it.coords(platform)) {
+        let base_path = maven_to_path(coord);
+        let src = from.join(&base_path);
+        let dest = to.join(&base_path);
 
-            let dir = file_path.parent().unwrap();
-            let url = format!("{base_url}/{base_path}");
-            let req = reqwest::blocking::get(&url)?;
-
-            if req.status() == StatusCode::OK {
-                fs::create_dir_all(dir)?;
-                fs::write(file_path, req.bytes()?)?;
-            } else {
-                let url = format!("{mc_libs_url}/{base_path}");
-                let req = reqwest::blocking::get(&url)?;
-
-                if req.status() == StatusCode::OK {
-                    fs::create_dir_all(dir)?;
-                    fs::write(file_path, req.bytes()?)?;
-                } else {
-                    eprintln!("An error occured downloading from: {url}");
-                }
+        if !src.exists() || dest.exists() {
+            continue;
+        }
+
+        fs::create_dir_all(dest.parent().unwrap())?;
+
+        if fs::hard_link(&src, &dest).is_err() {
+            fs::copy(&src, &dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the natives JARs of libraries that have them into `natives_dir`. Only older
+/// versions list natives this way; newer ones let LWJGL extract its own.
+pub fn extract_natives(
+    libraries: &[Library],
+    platform: &Platform,
+    lib_path: &Path,
+    natives_dir: &Path,
+) -> Result<()> {
+    for lib in libraries.iter().filter(|it| it.allowed(platform)) {
+        let Some(classifier) = lib.natives_classifier(platform) else {
+            continue;
+        };
+
+        let jar = lib_path.join(maven_to_path(format!("{}:{classifier}", lib.name)));
+        let exclude = lib.extract.clone().unwrap_or_default().exclude;
+        let mut zip = ZipArchive::new(File::open(&jar)?)?;
+
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i)?;
+
+            let Some(name) = entry.enclosed_name() else {
+                continue;
+            };
+
+            if entry.is_dir()
+                || exclude
+                    .iter()
+                    .any(|it| name.to_string_lossy().starts_with(it.as_str()))
+            {
+                continue;
             }
 
-            Ok(())
-        })
-        .flatten()
-        .collect::<Vec<_>>();
+            let out = natives_dir.join(name);
+
+            fs::create_dir_all(out.parent().unwrap())?;
+            io::copy(&mut entry, &mut File::create(out)?)?;
+        }
+    }
 
     Ok(())
 }
@@ -1,11 +1,14 @@
 use crate::{
     download::download_if_needed,
+    libraries::Library,
     meta::{MOJANG_META_URL, MetaIndex},
-    profile::NeoProfile,
+    placeholders::replace_tokens,
+    profile::{GameArguments, NeoProfile},
+    rules::{Features, Platform, Rule, rules_allow},
     side::Side,
 };
 use anyhow::Result;
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::Path};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -27,7 +30,123 @@ pub struct VersionDownloads {
 #[serde(rename_all = "camelCase")]
 pub struct VersionManifest {
     // There are a lot of other fields here but I don't really care about them.
+    pub id: String,
+    pub main_class: String,
     pub downloads: VersionDownloads,
+    #[serde(default)]
+    pub libraries: Vec<Library>,
+    pub arguments: Option<GameArguments>,
+    /// The space-separated game arguments of versions older than 1.13.
+    pub minecraft_arguments: Option<String>,
+    pub asset_index: Option<AssetIndexInfo>,
+    pub logging: Option<Logging>,
+    pub java_version: Option<JavaVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JavaVersion {
+    /// The name of the Java runtime Mojang's launcher ships for this version.
+    pub component: String,
+    pub major_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Logging {
+    pub client: Option<LoggingConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingConfig {
+    /// The JVM argument that points the game at the config, with a `${path}` placeholder.
+    pub argument: String,
+    pub file: LoggingFile,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+impl VersionManifest {
+    /// The version's arguments, converting the legacy `minecraftArguments` string if needed.
+    /// Legacy versions don't list JVM arguments, so the launcher's defaults are used.
+    pub fn game_arguments(&self) -> GameArguments {
+        if let Some(args) = &self.arguments {
+            return args.clone();
+        }
+
+        let game = self
+            .minecraft_arguments
+            .iter()
+            .flat_map(|it| it.split_whitespace())
+            .map(|it| Argument::Plain(it.into()))
+            .collect();
+
+        let jvm = [
+            "-Djava.library.path=${natives_directory}",
+            "-cp",
+            "${classpath}",
+        ]
+        .map(|it| Argument::Plain(it.into()))
+        .into();
+
+        GameArguments { game, jvm }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetIndexInfo {
+    pub id: String,
+    pub sha1: String,
+    pub size: u64,
+    pub total_size: u64,
+    pub url: String,
+}
+
+/// A game or JVM argument from a version JSON, optionally limited by rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Argument {
+    Plain(String),
+    Conditional {
+        rules: Vec<Rule>,
+        value: ArgumentValue,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ArgumentValue {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl Argument {
+    /// The argument's values, or nothing if its rules don't allow it.
+    pub fn values(&self, platform: &Platform, features: &Features) -> Vec<String> {
+        match self {
+            Self::Plain(value) => vec![value.clone()],
+
+            Self::Conditional { rules, .. } if !rules_allow(rules, platform, features) => {
+                Vec::new()
+            }
+
+            Self::Conditional { value, .. } => match value {
+                ArgumentValue::Single(value) => vec![value.clone()],
+                ArgumentValue::Many(values) => values.clone(),
+            },
+        }
+    }
 }
 
 impl VersionManifest {
@@ -39,56 +158,89 @@ impl VersionManifest {
     }
 
     pub fn mappings(&self, side: Side) -> String {
+        self.mappings_download(side).url.clone()
+    }
+
+    pub fn mappings_download(&self, side: Side) -> &FileDownload {
         match side {
-            Side::Client => self.downloads.client_mappings.url.clone(),
-            Side::Server => self.downloads.server_mappings.url.clone(),
+            Side::Client => &self.downloads.client_mappings,
+            Side::Server => &self.downloads.server_mappings,
         }
     }
 }
 
-pub async fn download_mc_jars(
-    data: &NeoProfile,
-    vars: &HashMap<String, String>,
-    side: Side,
-    lib_path: &PathBuf,
-    base_path: &PathBuf,
-) -> Result<()> {
+/// Fetches the raw version JSON for a Minecraft version.
+pub async fn fetch_version_json(version: impl AsRef<str>) -> Result<String> {
     let meta = reqwest::get(MOJANG_META_URL)
         .await?
         .json::<MetaIndex>()
         .await?;
 
-    let version_info = reqwest::get(
-        meta.versions
-            .into_iter()
-            .find(|v| v.id == data.minecraft)
-            .ok_or(anyhow!("Failed to find Minecraft version info!"))?
-            .url,
-    )
-    .await?
-    .json::<VersionManifest>()
-    .await?;
-
-    download_if_needed(
-        data.data("MINECRAFT_JAR", side, lib_path, base_path),
-        version_info.jar(side),
-    )
-    .await?;
-
-    download_if_needed(
-        data.data("MOJMAPS", side, lib_path, base_path),
-        version_info.mappings(side),
-    )
-    .await?;
+    let info = meta
+        .versions
+        .into_iter()
+        .find(|v| v.id == version.as_ref())
+        .ok_or(anyhow!("Failed to find Minecraft version info!"))?;
+
+    Ok(reqwest::get(info.url).await?.text().await?)
+}
+
+pub async fn fetch_version_manifest(version: impl AsRef<str>) -> Result<VersionManifest> {
+    Ok(serde_json::from_str(&fetch_version_json(version).await?)?)
+}
+
+/// The Minecraft files an install needs, as `(path, url)` pairs.
+pub fn mc_jar_downloads(
+    data: &NeoProfile,
+    version_info: &VersionManifest,
+    vars: &HashMap<String, String>,
+    side: Side,
+    work_dir: &Path,
+    lib_path: &Path,
+    base_path: &Path,
+) -> Result<Vec<(String, String)>> {
+    let mut downloads = vec![
+        (
+            data.data("MINECRAFT_JAR", side, lib_path, base_path)?,
+            version_info.jar(side),
+        ),
+        (
+            data.data("MOJMAPS", side, lib_path, base_path)?,
+            version_info.mappings(side),
+        ),
+    ];
 
     if side == Side::Server {
-        let mut server_path = data.server_jar_path.clone();
+        let server_path = work_dir.join(replace_tokens(vars, &data.server_jar_path)?);
 
-        for (k, v) in vars {
-            server_path = server_path.replace(&format!("{{{k}}}"), v);
-        }
+        downloads.push((
+            server_path.to_str().unwrap().into(),
+            version_info.jar(Side::Server),
+        ));
+    }
+
+    Ok(downloads)
+}
 
-        download_if_needed(server_path, version_info.jar(Side::Server)).await?;
+pub async fn download_mc_jars(
+    data: &NeoProfile,
+    version_info: &VersionManifest,
+    vars: &HashMap<String, String>,
+    side: Side,
+    work_dir: &Path,
+    lib_path: &Path,
+    base_path: &Path,
+) -> Result<()> {
+    for (path, url) in mc_jar_downloads(
+        data,
+        version_info,
+        vars,
+        side,
+        work_dir,
+        lib_path,
+        base_path,
+    )? {
+        download_if_needed(path, url).await?;
     }
 
     Ok(())
@@ -0,0 +1,166 @@
+use crate::{
+    bundler::{BUNDLER_EXTRACT_TASK, run_bundler_extract},
+    download::{Download, download_all},
+    manifest::fetch_version_manifest,
+    processors::Processor,
+    side::Side,
+};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+use tokio::runtime::Handle;
+use zip::ZipArchive;
+
+pub const INSTALLERTOOLS: &[&str] = &[
+    "net.neoforged.installertools:installertools",
+    "net.minecraftforge:installertools",
+];
+
+pub type NativeTask = fn(&[String], &Path) -> Result<()>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct NativeProcessor {
+    /// The processor's `group:artifact`, without a version.
+    pub artifacts: &'static [&'static str],
+    /// The value of the processor's `--task` argument, if it has one.
+    pub task: Option<&'static str>,
+    pub run: NativeTask,
+}
+
+pub const NATIVE_PROCESSORS: &[NativeProcessor] = &[
+    NativeProcessor {
+        artifacts: INSTALLERTOOLS,
+        task: Some(BUNDLER_EXTRACT_TASK),
+        run: run_bundler_extract,
+    },
+    NativeProcessor {
+        artifacts: INSTALLERTOOLS,
+        task: Some("EXTRACT_FILES"),
+        run: run_extract_files,
+    },
+    NativeProcessor {
+        artifacts: INSTALLERTOOLS,
+        task: Some("DOWNLOAD_MOJMAPS"),
+        run: run_download_mojmaps,
+    },
+];
+
+pub fn find_native(proc: &Processor) -> Option<&'static NativeProcessor> {
+    NATIVE_PROCESSORS
+        .iter()
+        .find(|it| it.artifacts.contains(&proc.artifact()) && it.task == proc.task())
+}
+
+fn run_extract_files(args: &[String], work_dir: &Path) -> Result<()> {
+    let mut archive = None;
+    let mut from = Vec::new();
+    let mut to = Vec::new();
+    let mut exec = Vec::new();
+    let mut optional = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(|v| v.as_str())
+                .ok_or(anyhow!("Missing value for EXTRACT_FILES argument: {arg}"))
+        };
+
+        match arg.as_str() {
+            "--task" => {
+                value()?;
+            }
+
+            "--archive" => archive = Some(value()?),
+            "--from" => from.push(value()?),
+            "--to" => to.push(work_dir.join(value()?)),
+            "--exec" => exec.push(work_dir.join(value()?)),
+            "--optional" => optional.push(work_dir.join(value()?)),
+            other => return Err(anyhow!("Unknown EXTRACT_FILES argument: {other}")),
+        }
+    }
+
+    let archive = archive.ok_or(anyhow!("EXTRACT_FILES requires --archive"))?;
+
+    if from.len() != to.len() {
+        return Err(anyhow!("EXTRACT_FILES requires one --to for every --from"));
+    }
+
+    let mut zip = ZipArchive::new(File::open(work_dir.join(archive))?)?;
+
+    for (from, to) in from.into_iter().zip(to) {
+        if optional.contains(&to) && to.exists() {
+            continue;
+        }
+
+        let mut entry = zip
+            .by_name(from)
+            .map_err(|_| anyhow!("Failed to find {from} in archive: {archive}"))?;
+
+        let mut content = Vec::new();
+
+        entry.read_to_end(&mut content)?;
+
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&to, content)?;
+
+        #[cfg(unix)]
+        if exec.contains(&to) {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&to, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn run_download_mojmaps(args: &[String], work_dir: &Path) -> Result<()> {
+    let mut version = None;
+    let mut side = None;
+    let mut output = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--task" => {
+                args.next();
+            }
+
+            "--version" => version = args.next(),
+            "--side" => side = args.next(),
+            "--output" => output = args.next(),
+            other => return Err(anyhow!("Unknown DOWNLOAD_MOJMAPS argument: {other}")),
+        }
+    }
+
+    let version = version.ok_or(anyhow!("DOWNLOAD_MOJMAPS requires --version"))?;
+    let output = output.ok_or(anyhow!("DOWNLOAD_MOJMAPS requires --output"))?;
+
+    let side = Side::from_str(
+        side.ok_or(anyhow!("DOWNLOAD_MOJMAPS requires --side"))?,
+        true,
+    )
+    .map_err(|err| anyhow!("Invalid side for DOWNLOAD_MOJMAPS: {err}"))?;
+
+    let manifest = Handle::current().block_on(fetch_version_manifest(version))?;
+    let mappings = manifest.mappings_download(side);
+
+    let download = Download {
+        path: work_dir.join(output),
+        urls: vec![mappings.url.clone()],
+        sha1: Some(mappings.sha1.clone()),
+    };
+
+    match download_all(&[download])?.into_iter().next() {
+        Some((_, err)) => Err(err),
+        None => Ok(()),
+    }
+}
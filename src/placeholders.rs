@@ -0,0 +1,178 @@
+use crate::maven::maven_to_path;
+use anyhow::Result;
+use std::{collections::HashMap, path::Path};
+
+/// Expands `{KEY}` tokens and `'literal'` sections in a value, following the installer spec.
+/// A backslash escapes the character after it.
+pub fn replace_tokens(vars: &HashMap<String, String>, value: &str) -> Result<String> {
+    let mut out = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.push(
+                chars
+                    .next()
+                    .ok_or(anyhow!("Illegal pattern (bad escape): {value}"))?,
+            ),
+
+            '{' | '\'' => {
+                let close = if c == '{' { '}' } else { '\'' };
+                let mut key = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('\\') => key.push(
+                            chars
+                                .next()
+                                .ok_or(anyhow!("Illegal pattern (bad escape): {value}"))?,
+                        ),
+
+                        Some(d) if d == close => break,
+                        Some(d) => key.push(d),
+                        None => return Err(anyhow!("Illegal pattern (unclosed {c}): {value}")),
+                    }
+                }
+
+                if c == '\'' {
+                    out.push_str(&key);
+                } else {
+                    out.push_str(
+                        vars.get(&key)
+                            .ok_or(anyhow!("Unknown placeholder {{{key}}} in: {value}"))?,
+                    );
+                }
+            }
+
+            c => out.push(c),
+        }
+    }
+
+    Ok(out)
+}
+
+fn artifact(value: &str) -> Option<&str> {
+    value.strip_prefix('[')?.strip_suffix(']')
+}
+
+fn literal(value: &str) -> Option<&str> {
+    value.strip_prefix('\'')?.strip_suffix('\'')
+}
+
+/// Resolves a processor argument: `[maven:coord]` becomes a library path, and anything
+/// else has its tokens replaced.
+pub fn resolve_arg(vars: &HashMap<String, String>, lib_path: &Path, value: &str) -> Result<String> {
+    match artifact(value) {
+        Some(coord) => Ok(lib_path.join(maven_to_path(coord)).to_str().unwrap().into()),
+        None => replace_tokens(vars, value),
+    }
+}
+
+/// Resolves an install profile data value: `[maven:coord]` becomes a library path,
+/// `'literal'` is used as-is and anything else is a file extracted from the installer.
+pub fn resolve_data(value: &str, lib_path: &Path, base_path: &Path) -> Result<String> {
+    if value.is_empty() {
+        return Err(anyhow!("Empty data value"));
+    }
+
+    if let Some(coord) = artifact(value) {
+        Ok(lib_path.join(maven_to_path(coord)).to_str().unwrap().into())
+    } else if let Some(literal) = literal(value) {
+        Ok(literal.into())
+    } else {
+        Ok(base_path
+            .join(value.trim_start_matches('/'))
+            .to_str()
+            .unwrap()
+            .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("SIDE".into(), "server".into()),
+            ("MOJMAPS".into(), "/libs/server-mappings.txt".into()),
+        ])
+    }
+
+    #[test]
+    fn expands_tokens() {
+        assert_eq!(
+            replace_tokens(&vars(), "--mappings={MOJMAPS}").unwrap(),
+            "--mappings=/libs/server-mappings.txt"
+        );
+
+        assert_eq!(
+            replace_tokens(&vars(), "{SIDE}-{SIDE}").unwrap(),
+            "server-server"
+        );
+    }
+
+    #[test]
+    fn literal_sections() {
+        assert_eq!(replace_tokens(&vars(), "'{SIDE}'").unwrap(), "{SIDE}");
+        assert_eq!(replace_tokens(&vars(), "a'{b'{SIDE}").unwrap(), "a{bserver");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(replace_tokens(&vars(), r"\{SIDE\}").unwrap(), "{SIDE}");
+        assert_eq!(replace_tokens(&vars(), r"it\'s").unwrap(), "it's");
+        assert_eq!(replace_tokens(&vars(), r"'it\'s'").unwrap(), "it's");
+        assert_eq!(replace_tokens(&vars(), r"{SI\DE}").unwrap(), "server");
+    }
+
+    #[test]
+    fn errors() {
+        let err = |value| replace_tokens(&vars(), value).unwrap_err().to_string();
+
+        assert!(err("{SIDE").contains("unclosed {"));
+        assert!(err("'literal").contains("unclosed '"));
+        assert!(err(r"trailing\").contains("bad escape"));
+        assert!(err("{MISSING}").contains("Unknown placeholder {MISSING}"));
+    }
+
+    #[test]
+    fn arguments() {
+        let lib_path = Path::new("/libs");
+
+        assert_eq!(
+            resolve_arg(&vars(), lib_path, "[net.neoforged:neoform:1.21.1@zip]").unwrap(),
+            "/libs/net/neoforged/neoform/1.21.1/neoform-1.21.1.zip"
+        );
+
+        assert_eq!(resolve_arg(&vars(), lib_path, "{SIDE}").unwrap(), "server");
+    }
+
+    #[test]
+    fn data_values() {
+        let lib_path = Path::new("/libs");
+        let base_path = Path::new("/installer");
+
+        assert_eq!(
+            resolve_data(
+                "[net.minecraft:server:1.21.1:mappings@txt]",
+                lib_path,
+                base_path
+            )
+            .unwrap(),
+            "/libs/net/minecraft/server/1.21.1/server-1.21.1-mappings.txt"
+        );
+
+        assert_eq!(
+            resolve_data("'neoforge-21.1.77'", lib_path, base_path).unwrap(),
+            "neoforge-21.1.77"
+        );
+
+        assert_eq!(
+            resolve_data("/data/server.lzma", lib_path, base_path).unwrap(),
+            "/installer/data/server.lzma"
+        );
+
+        assert!(resolve_data("", lib_path, base_path).is_err());
+    }
+}
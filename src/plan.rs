@@ -0,0 +1,300 @@
+use crate::{
+    install::{
+        InstallOptions, fetch_installer, needs_java, processor_context, runs_on, side_libraries,
+    },
+    java::{JAVA_EXE, select_java},
+    libraries::{MOJANG_LIBRARIES_URL, fetch_mirror},
+    manifest::{fetch_version_manifest, mc_jar_downloads},
+    maven::maven_to_path,
+    processors::{
+        Processor, ProcessorCommand, ProcessorContext, java_command, main_class, resolve_command,
+        uses_argfile,
+    },
+    profile::setup_vars,
+    side::Side,
+};
+use anyhow::Result;
+use clap::ValueEnum;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PlanFormat {
+    /// A readable summary.
+    #[default]
+    Human,
+    /// The full plan as JSON.
+    Json,
+}
+
+/// Everything an install would do, without doing it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPlan {
+    pub neo: String,
+    pub minecraft: String,
+    pub side: Side,
+    pub dir: PathBuf,
+    pub vars: BTreeMap<String, String>,
+    pub libraries: Vec<PlannedLibrary>,
+    pub minecraft_files: Vec<PlannedFile>,
+    pub processors: Vec<PlannedProcessor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedLibrary {
+    pub name: String,
+    pub path: PathBuf,
+    pub url: String,
+    pub fallback_url: String,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub url: String,
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedProcessor {
+    pub index: usize,
+    pub jar: String,
+    pub sides: Option<Vec<Side>>,
+    pub runs: bool,
+    pub native: bool,
+    /// Whether the command line would be passed to Java through an argfile.
+    pub argfile: bool,
+    pub command: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Works out what installing with `opts` would do. Only metadata is downloaded: the
+/// installer JAR, the mirror list and the Minecraft version manifest. The installer goes
+/// in a temporary directory, so nothing in the target directory is touched.
+pub async fn plan(opts: &InstallOptions) -> Result<InstallPlan> {
+    let work_dir = std::path::absolute(&opts.target)?;
+    let download_dir = env::temp_dir().join(format!("neo-install-plan-{}", process::id()));
+    let result = plan_into(opts, &work_dir, &download_dir).await;
+
+    if download_dir.exists() {
+        fs::remove_dir_all(&download_dir)?;
+    }
+
+    result
+}
+
+async fn plan_into(
+    opts: &InstallOptions,
+    work_dir: &Path,
+    download_dir: &Path,
+) -> Result<InstallPlan> {
+    let [side] = opts.side.sides() else {
+        return Err(anyhow!("Plans can only be made for one side at a time!"));
+    };
+
+    let side = *side;
+    let base_path = work_dir.join(".installer");
+    let lib_path = work_dir.join("libraries");
+    let installer = fetch_installer(&opts.neo, download_dir).await?;
+    let profile = &installer.profile;
+
+    // The variables point where a real install would put the installer.
+    let installer_jar = base_path.join("installer.jar");
+    let vars = setup_vars(profile, side, &lib_path, &base_path, &installer_jar)?;
+
+    let mirror = fetch_mirror(profile).await?;
+    let version_info = fetch_version_manifest(&profile.minecraft).await?;
+
+    let minecraft_files = mc_jar_downloads(
+        profile,
+        &version_info,
+        &vars,
+        side,
+        work_dir,
+        &lib_path,
+        &base_path,
+    )?
+    .into_iter()
+    .map(|(path, url)| {
+        let path = PathBuf::from(path);
+
+        PlannedFile {
+            cached: path.exists(),
+            path,
+            url,
+        }
+    })
+    .collect();
+
+    let required_java = version_info
+        .java_version
+        .as_ref()
+        .map(|it| it.major_version);
+
+    let needs_java = needs_java(
+        opts,
+        profile.processors.iter().filter(|it| runs_on(it, side)),
+    );
+
+    // A plan is still useful without a suitable Java, and shouldn't download one, so
+    // this only warns.
+    let java = match needs_java {
+        true => select_java(opts.java.java.as_deref(), required_java)
+            .map(|it| it.command())
+            .unwrap_or_else(|err| {
+                eprintln!("Warning: {err}");
+                opts.java.java.clone().unwrap_or(JAVA_EXE.into())
+            }),
+
+        false => opts.java.java.clone().unwrap_or(JAVA_EXE.into()),
+    };
+
+    let ctx = processor_context(opts, work_dir, &base_path, vars.clone(), java)?;
+
+    let libraries = side_libraries(&installer, &version_info, side, &opts.platform)
+        .iter()
+        .flat_map(|lib| lib.coords(&opts.platform))
+        .map(|name| {
+            let base_path = maven_to_path(&name);
+            let path = lib_path.join(&base_path);
+
+            PlannedLibrary {
+                name,
+                cached: path.exists(),
+                url: format!("{mirror}/{base_path}"),
+                fallback_url: format!("{MOJANG_LIBRARIES_URL}/{base_path}"),
+                path,
+            }
+        })
+        .collect();
+
+    let processors = profile
+        .processors
+        .iter()
+        .enumerate()
+        .map(|(index, proc)| {
+            let command = plan_command(proc, &ctx);
+
+            PlannedProcessor {
+                index,
+                jar: proc.jar.clone(),
+                sides: proc.sides.clone(),
+                runs: runs_on(proc, side),
+                native: command.as_ref().is_ok_and(|(native, _)| *native),
+                argfile: command
+                    .as_ref()
+                    .is_ok_and(|(native, it)| !native && uses_argfile(&ctx, it)),
+                error: command.as_ref().err().map(|it| format!("{it:#}")),
+                command: command.map(|(_, it)| it).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    Ok(InstallPlan {
+        neo: opts.neo.clone(),
+        minecraft: profile.minecraft.clone(),
+        side,
+        dir: work_dir.to_path_buf(),
+        vars: vars.into_iter().collect(),
+        libraries,
+        minecraft_files,
+        processors,
+    })
+}
+
+/// The command a processor would run with. The main class is only known once the
+/// processor JAR has been downloaded.
+fn plan_command(proc: &Processor, ctx: &ProcessorContext) -> Result<(bool, Vec<String>)> {
+    match resolve_command(proc, ctx)? {
+        ProcessorCommand::Native { args, .. } => {
+            Ok((true, [vec!["<native>".into()], args].concat()))
+        }
+
+        ProcessorCommand::Java { jar, args } => {
+            let main = main_class(&jar).unwrap_or("<Main-Class>".into());
+
+            Ok((false, java_command(proc, ctx, &jar, main, args)))
+        }
+    }
+}
+
+impl InstallPlan {
+    pub fn print(&self, format: PlanFormat) -> Result<()> {
+        match format {
+            PlanFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+            PlanFormat::Human => self.print_human(),
+        }
+
+        Ok(())
+    }
+
+    fn print_human(&self) {
+        let status = |cached: bool| if cached { "cached" } else { "download" };
+
+        println!(
+            "NeoForge {} (Minecraft {}, {}) in {}",
+            self.neo,
+            self.minecraft,
+            self.side.get(),
+            self.dir.display()
+        );
+
+        println!("\nVariables:");
+
+        for (key, value) in &self.vars {
+            println!("  {key} = {value}");
+        }
+
+        println!("\nLibraries:");
+
+        for lib in &self.libraries {
+            println!("  [{}] {}", status(lib.cached), lib.name);
+            println!("      {}", lib.url);
+            println!("      {}", lib.fallback_url);
+        }
+
+        println!("\nMinecraft:");
+
+        for file in &self.minecraft_files {
+            println!("  [{}] {}", status(file.cached), file.path.display());
+            println!("      {}", file.url);
+        }
+
+        println!("\nProcessors:");
+
+        for proc in &self.processors {
+            let sides = match &proc.sides {
+                Some(sides) => sides
+                    .iter()
+                    .map(|it| it.get())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => "all".into(),
+            };
+
+            let run = match (proc.runs, proc.native, proc.argfile) {
+                (false, _, _) => "skip",
+                (true, true, _) => "native",
+                (true, false, true) => "java, argfile",
+                (true, false, false) => "java",
+            };
+
+            println!("  #{} [{run}] {} (sides: {sides})", proc.index, proc.jar);
+
+            if let Some(error) = &proc.error {
+                println!("      error: {error}");
+            } else if proc.runs {
+                println!("      {}", proc.command.join(" "));
+            }
+        }
+    }
+}
@@ -1,9 +1,32 @@
-use crate::{maven::maven_to_path, side::Side};
-use anyhow::Result;
+use crate::{
+    argfile::{ARGFILE_THRESHOLD, command_len, write_argfile},
+    graph::ProcessorIo,
+    maven::maven_to_path,
+    native::{NativeProcessor, find_native},
+    placeholders::resolve_arg,
+    sandbox::{Sandbox, SandboxPolicy},
+    side::Side,
+    signal::{interrupted, terminate_child},
+    util::{find_executable, jvm_tmp_dir},
+};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
 use itertools::Itertools;
-use std::{collections::HashMap, fs::File, io::Read, path::PathBuf, process::Command};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::{BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 use zip::ZipArchive;
 
+/// How many lines of a failed processor's output get printed.
+pub const FAILURE_EXCERPT_LINES: usize = 30;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Processor {
@@ -11,77 +34,117 @@ pub struct Processor {
     pub jar: String,
     pub classpath: Vec<String>,
     pub args: Vec<String>,
+    pub outputs: Option<HashMap<String, String>>,
 }
 
-pub async fn run_processor(
-    proc: &Processor,
-    vars: &HashMap<String, String>,
-    lib_path: &PathBuf,
-    work_dir: &PathBuf,
-    java: &String,
-) -> Result<()> {
-    let jar = lib_path.join(maven_to_path(&proc.jar));
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ProcessorMode {
+    /// Only run processors that have a Rust implementation, failing before the install
+    /// starts otherwise. Only installertools' BUNDLER_EXTRACT, EXTRACT_FILES and
+    /// DOWNLOAD_MOJMAPS tasks have one, so this can't finish installs that patch the game
+    /// with jarsplitter, binarypatcher or AutoRenamingTool yet.
+    Native,
+    /// Run processors in Rust where possible, falling back to Java.
+    #[default]
+    Auto,
+    /// Always run processors with Java.
+    Java,
+}
 
-    if !jar.exists() {
-        return Err(anyhow!("Failed to find processor JAR: {}", proc.jar));
-    }
+#[derive(Debug, Clone)]
+pub struct ProcessorContext {
+    pub vars: HashMap<String, String>,
+    pub lib_path: PathBuf,
+    pub work_dir: PathBuf,
+    pub installer_dir: PathBuf,
+    pub log_dir: PathBuf,
+    pub args_dir: PathBuf,
+    pub java: String,
+    pub jvm_args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub mode: ProcessorMode,
+    pub verbose: bool,
+    pub timeout: Option<Duration>,
+    pub argfile: bool,
+    pub sandbox: bool,
+}
 
-    let mut classpath = Vec::new();
+#[derive(Debug, Clone, Copy)]
+enum ProcessOutcome {
+    Exited(ExitStatus),
+    TimedOut,
+    Interrupted,
+}
 
-    for item in &proc.classpath {
-        let item_path = lib_path.join(maven_to_path(item));
+impl Processor {
+    pub fn task(&self) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|v| v == "--task")
+            .and_then(|i| self.args.get(i + 1))
+            .map(|v| v.as_str())
+    }
 
-        if !item_path.exists() {
-            eprintln!("Failed to find classpath JAR: {}", item);
-            continue;
+    /// The processor's `group:artifact`, without a version or classifier.
+    pub fn artifact(&self) -> &str {
+        match self.jar.match_indices(':').nth(1) {
+            Some((idx, _)) => &self.jar[..idx],
+            None => &self.jar,
         }
+    }
 
-        classpath.push(
-            item_path
-                .strip_prefix(work_dir)
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string(),
-        );
+    /// The processor's artifact id, without a group or version.
+    pub fn artifact_id(&self) -> &str {
+        self.jar.split(':').nth(1).unwrap_or(&self.jar)
     }
+}
+
+/// How a processor will be run, with all of its arguments resolved.
+#[derive(Debug, Clone)]
+pub enum ProcessorCommand {
+    Native {
+        native: &'static NativeProcessor,
+        args: Vec<String>,
+    },
+
+    Java {
+        jar: PathBuf,
+        args: Vec<String>,
+    },
+}
 
+pub fn resolve_command(proc: &Processor, ctx: &ProcessorContext) -> Result<ProcessorCommand> {
     let args = proc
         .args
         .iter()
-        .map(|s| {
-            let mut s = s.clone();
+        .map(|it| resolve_arg(&ctx.vars, &ctx.lib_path, it))
+        .collect::<Result<Vec<_>>>()
+        .with_context(|| format!("Failed to resolve arguments for processor: {}", proc.jar))?;
 
-            for (k, v) in vars {
-                s = s.replace(&format!("{{{k}}}"), v);
-            }
+    match (ctx.mode, find_native(proc)) {
+        (ProcessorMode::Java, _) | (ProcessorMode::Auto, None) => Ok(ProcessorCommand::Java {
+            jar: ctx.lib_path.join(maven_to_path(&proc.jar)),
+            args,
+        }),
 
-            if s.starts_with('[') && s.ends_with(']') {
-                s = lib_path
-                    .join(maven_to_path(
-                        s.trim_start_matches('[').trim_end_matches(']'),
-                    ))
-                    .strip_prefix(work_dir)
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-            }
-
-            s
-        })
-        .collect_vec();
+        (_, Some(native)) => Ok(ProcessorCommand::Native { native, args }),
 
-    classpath.push(jar.to_str().unwrap().into());
+        (ProcessorMode::Native, None) => Err(anyhow!(
+            "No native implementation for processor: {}",
+            proc.jar
+        )),
+    }
+}
 
-    let file = File::open(&jar)?;
+pub fn main_class(jar: &Path) -> Result<String> {
+    let file = File::open(jar)?;
     let mut zip = ZipArchive::new(file)?;
     let entry = zip.by_name("META-INF/MANIFEST.MF");
 
     if entry.is_err() {
         return Err(anyhow!(
             "Failed to find META-INF/MANIFEST.MF in JAR: {}",
-            proc.jar
+            jar.display()
         ));
     }
 
@@ -98,37 +161,390 @@ pub async fn run_processor(
         .map(|v| v.replace("\r", "").to_string())
         .collect_vec();
 
-    let main = manifest
+    manifest
         .iter()
         .find(|v| v.starts_with("Main-Class:"))
-        .map(|v| v.split(": ").last())
-        .flatten()
+        .and_then(|v| v.split(": ").last())
         .map(|v| v.to_string())
-        .ok_or(anyhow!("Failed to find main class in JAR: {}", proc.jar))?;
+        .ok_or(anyhow!(
+            "Failed to find main class in JAR: {}",
+            jar.display()
+        ))
+}
+
+/// Builds the full `java` command line for a processor.
+pub fn java_command(
+    proc: &Processor,
+    ctx: &ProcessorContext,
+    jar: &Path,
+    main: String,
+    args: Vec<String>,
+) -> Vec<String> {
+    let mut classpath = proc
+        .classpath
+        .iter()
+        .map(|item| {
+            let item_path = ctx.lib_path.join(maven_to_path(item));
+
+            item_path
+                .strip_prefix(&ctx.work_dir)
+                .unwrap_or(&item_path)
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect_vec();
+
+    classpath.push(jar.to_str().unwrap().into());
 
     #[cfg(not(windows))]
     let classpath = classpath.join(":");
     #[cfg(windows)]
     let classpath = classpath.join(";");
-    let mut cmd = Vec::new();
+    let mut cmd = vec![ctx.java.clone()];
 
-    cmd.push(java.into());
-    cmd.push("-cp".into());
-    cmd.push(classpath);
-    cmd.push(main);
+    cmd.extend(ctx.jvm_args.iter().cloned());
+    cmd.extend(["-cp".into(), classpath, main]);
     cmd.extend(args);
+    cmd
+}
 
-    println!("Exec: {}", cmd.join(" "));
+/// Whether a processor's command line is passed through an argfile, which happens when
+/// it's too long for some OSes or if that's always asked for.
+pub fn uses_argfile(ctx: &ProcessorContext, cmd: &[String]) -> bool {
+    ctx.argfile || command_len(cmd) > ARGFILE_THRESHOLD
+}
 
-    let res = Command::new(cmd.remove(0))
-        .args(cmd)
-        .current_dir(work_dir)
-        .spawn()?
-        .wait()?;
+pub async fn run_processor(proc: &Processor, index: usize, ctx: &ProcessorContext) -> Result<()> {
+    let work_dir = ctx.work_dir.as_path();
+
+    let (jar, args) = match resolve_command(proc, ctx)? {
+        ProcessorCommand::Java { jar, args } => (jar, args),
+
+        ProcessorCommand::Native { native, args } => {
+            if ctx.verbose {
+                println!("Native: {} {}", proc.jar, args.join(" "));
+            } else {
+                println!("Running processor {index} natively: {}", proc.jar);
+            }
+
+            let work_dir = work_dir.to_path_buf();
+
+            tokio::task::spawn_blocking(move || (native.run)(&args, &work_dir))
+                .await?
+                .with_context(|| format!("Native processor {} failed", proc.jar))?;
+
+            if interrupted() {
+                return Err(anyhow!("Processor {index} ({}) was interrupted", proc.jar));
+            }
+
+            return Ok(());
+        }
+    };
+
+    if !jar.exists() {
+        return Err(anyhow!("Failed to find processor JAR: {}", proc.jar));
+    }
+
+    // Missing classpath JARs are left out, so the processor still gets a chance to run
+    // without them.
+    let mut available = proc.clone();
+
+    available.classpath.retain(|item| {
+        let exists = ctx.lib_path.join(maven_to_path(item)).exists();
+
+        if !exists {
+            eprintln!("Failed to find classpath JAR: {}", item);
+        }
+
+        exists
+    });
+
+    let main = main_class(&jar).with_context(|| format!("Invalid processor JAR: {}", proc.jar))?;
+    let mut cmd = java_command(&available, ctx, &jar, main, args);
+
+    if uses_argfile(ctx, &cmd) {
+        let argfile = ctx
+            .args_dir
+            .join(format!("{index}-{}.txt", proc.artifact_id()));
+
+        write_argfile(&argfile, &cmd[1..])?;
+
+        cmd = vec![ctx.java.clone(), format!("@{}", argfile.display())];
+    }
+
+    let log_path = ctx
+        .log_dir
+        .join(format!("{index}-{}.log", proc.artifact_id()));
+
+    if ctx.verbose {
+        println!("Exec: {}", cmd.join(" "));
+    } else {
+        println!("Running processor {index}: {}", proc.jar);
+    }
+
+    if let Some(tmp_dir) = jvm_tmp_dir(&ctx.jvm_args) {
+        fs::create_dir_all(tmp_dir)?;
+    }
+
+    let (sandbox, placeholders) = match ctx.sandbox {
+        true => {
+            let (policy, placeholders) = sandbox_policy(proc, ctx)?;
 
-    if !res.success() {
-        return Err(anyhow!("Processor failed!"));
+            match Sandbox::new(&policy) {
+                Ok(sandbox) => (Some(sandbox), placeholders),
+
+                Err(err) => {
+                    remove_placeholders(&placeholders);
+                    return Err(err);
+                }
+            }
+        }
+
+        false => (None, Vec::new()),
+    };
+
+    let result = tokio::task::spawn_blocking({
+        let cmd = cmd.clone();
+        let work_dir = work_dir.to_path_buf();
+        let log_path = log_path.clone();
+        let env = ctx.env.clone();
+        let verbose = ctx.verbose;
+        let timeout = ctx.timeout;
+
+        move || run_captured(&cmd, &env, &work_dir, &log_path, verbose, timeout, sandbox)
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|it| it)
+    .and_then(|(outcome, tail)| check_outcome(proc, index, ctx, &cmd, &log_path, outcome, &tail));
+
+    if result.is_err() {
+        remove_placeholders(&placeholders);
+    }
+
+    result
+}
+
+/// Turns how a processor's command ended into an error if it failed, printing the end of
+/// its output.
+fn check_outcome(
+    proc: &Processor,
+    index: usize,
+    ctx: &ProcessorContext,
+    cmd: &[String],
+    log_path: &Path,
+    outcome: ProcessOutcome,
+    tail: &[String],
+) -> Result<()> {
+    let status = match outcome {
+        ProcessOutcome::Exited(status) => status,
+
+        ProcessOutcome::Interrupted => {
+            return Err(anyhow!("Processor {index} ({}) was interrupted", proc.jar));
+        }
+
+        ProcessOutcome::TimedOut => {
+            eprintln!("Full log: {}", log_path.display());
+
+            return Err(anyhow!(
+                "Processor {index} ({}) timed out after {}s",
+                proc.jar,
+                ctx.timeout.unwrap_or_default().as_secs()
+            ));
+        }
+    };
+
+    if !status.success() {
+        eprintln!("Processor {index} ({}) failed. Command:", proc.jar);
+        eprintln!("  {}", cmd.join(" "));
+        eprintln!("Last {} lines of output:", tail.len());
+
+        for line in tail {
+            eprintln!("  {line}");
+        }
+
+        eprintln!("Full log: {}", log_path.display());
+
+        return Err(match status.code() {
+            Some(code) => anyhow!("Processor {} failed with exit code {code}", proc.jar),
+            None => anyhow!("Processor {} was terminated by a signal", proc.jar),
+        });
     }
 
     Ok(())
 }
+
+/// Read access to the libraries, the processor's inputs and the Java installation, and write
+/// access to the installer directory (which has the logs), the JVM's temp directory and the
+/// processor's outputs. Also returns the empty placeholder files it created for outputs.
+fn sandbox_policy(
+    proc: &Processor,
+    ctx: &ProcessorContext,
+) -> Result<(SandboxPolicy, Vec<PathBuf>)> {
+    let io = ProcessorIo::of(proc, ctx)?;
+    let mut policy = SandboxPolicy::default();
+
+    policy.read.push(ctx.lib_path.clone());
+    policy.read.extend(io.inputs);
+
+    if let Some(java_home) = find_executable(&ctx.java)
+        .and_then(|it| it.canonicalize().ok())
+        .and_then(|it| Some(it.parent()?.parent()?.to_path_buf()))
+    {
+        policy.read.push(java_home);
+    }
+
+    policy.write.push(ctx.installer_dir.clone());
+    policy.write.extend(jvm_tmp_dir(&ctx.jvm_args));
+
+    let mut placeholders = Vec::new();
+
+    // Landlock rules only cover paths that exist, so missing outputs are created empty.
+    // That lets the processor write each one without being able to create anything else
+    // next to them.
+    for output in io.outputs {
+        if io.output_dirs.contains(&output) {
+            fs::create_dir_all(&output)?;
+        } else if !output.exists() {
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            File::create(&output)?;
+            placeholders.push(output.clone());
+        }
+
+        policy.write.push(output);
+    }
+
+    Ok((policy, placeholders))
+}
+
+/// Removes the placeholders [`sandbox_policy`] created that a failed processor left empty,
+/// so they aren't mistaken for finished files on the next run.
+fn remove_placeholders(placeholders: &[PathBuf]) {
+    for path in placeholders {
+        if fs::metadata(path).is_ok_and(|it| it.len() == 0) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Runs a command, writing its stdout and stderr to `log_path` and returning how it ended
+/// and the last [`FAILURE_EXCERPT_LINES`] lines of output.
+fn run_captured(
+    cmd: &[String],
+    env: &[(String, String)],
+    work_dir: &Path,
+    log_path: &Path,
+    verbose: bool,
+    timeout: Option<Duration>,
+    sandbox: Option<Sandbox>,
+) -> Result<(ProcessOutcome, Vec<String>)> {
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let log = Arc::new(Mutex::new(File::create(log_path)?));
+    let tail = Arc::new(Mutex::new(VecDeque::with_capacity(FAILURE_EXCERPT_LINES)));
+
+    let mut command = Command::new(&cmd[0]);
+
+    command
+        .args(&cmd[1..])
+        .envs(env.iter().cloned())
+        .current_dir(work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let sandboxed = sandbox.is_some();
+
+    if let Some(sandbox) = sandbox {
+        sandbox.apply(&mut command);
+    }
+
+    let mut child = command.spawn().with_context(|| match sandboxed {
+        true => format!(
+            "Failed to start sandboxed processor: {} (the sandbox needs Landlock and unprivileged user namespaces)",
+            cmd[0]
+        ),
+        false => format!("Failed to start processor: {}", cmd[0]),
+    })?;
+
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|it| Box::new(it) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|it| Box::new(it) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|stream| {
+        let log = log.clone();
+        let tail = tail.clone();
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stream);
+            let mut buf = Vec::new();
+
+            while reader.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+                let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+
+                if verbose {
+                    println!("{line}");
+                }
+
+                let _ = writeln!(log.lock().unwrap(), "{line}");
+                let mut tail = tail.lock().unwrap();
+
+                if tail.len() == FAILURE_EXCERPT_LINES {
+                    tail.pop_front();
+                }
+
+                tail.push_back(line);
+                buf.clear();
+            }
+        })
+    })
+    .collect_vec();
+
+    let start = Instant::now();
+
+    let outcome = loop {
+        if let Some(status) = child.try_wait()? {
+            break ProcessOutcome::Exited(status);
+        }
+
+        if interrupted() {
+            terminate_child(&mut child)?;
+            break ProcessOutcome::Interrupted;
+        }
+
+        if timeout.is_some_and(|it| start.elapsed() > it) {
+            terminate_child(&mut child)?;
+            break ProcessOutcome::TimedOut;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    // A child interrupted by Ctrl-C usually exits on its own before we notice.
+    let outcome = match outcome {
+        ProcessOutcome::Exited(_) if interrupted() => ProcessOutcome::Interrupted,
+        other => other,
+    };
+
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    let tail = tail.lock().unwrap().iter().cloned().collect();
+
+    Ok((outcome, tail))
+}
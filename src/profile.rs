@@ -1,10 +1,12 @@
 use crate::{
     libraries::Library,
-    maven::maven_to_path,
+    manifest::{Argument, VersionManifest},
+    placeholders::resolve_data,
     processors::Processor,
     side::{Side, Sided},
 };
-use std::{collections::HashMap, path::PathBuf};
+use anyhow::{Context, Result};
+use std::{collections::HashMap, path::Path};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -29,21 +31,46 @@ pub struct NeoProfile {
 #[serde(rename_all = "camelCase")]
 pub struct NeoVersionManifest {
     pub id: String,
+    #[serde(default)]
     pub time: String,
+    #[serde(default)]
     pub release_time: String,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default)]
     pub kind: String,
     pub main_class: String,
-    pub inherits_from: String,
+    pub inherits_from: Option<String>,
+    #[serde(default)]
     pub arguments: GameArguments,
+    #[serde(default)]
     pub libraries: Vec<Library>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct GameArguments {
-    pub game: Vec<String>,
-    pub jvm: Vec<String>,
+    pub game: Vec<Argument>,
+    pub jvm: Vec<Argument>,
+}
+
+impl NeoVersionManifest {
+    /// Merges in the version this one inherits from, the way the launcher does: the child's
+    /// main class wins, arguments are appended to the parent's, and the child's libraries
+    /// come first, replacing any parent library for the same artifact.
+    pub fn inherit(mut self, parent: VersionManifest) -> Self {
+        let args = parent.game_arguments();
+        let mut libraries = self.libraries;
+
+        for lib in parent.libraries {
+            if !libraries.iter().any(|it| it.key() == lib.key()) {
+                libraries.push(lib);
+            }
+        }
+
+        self.arguments.game = [args.game, self.arguments.game].concat();
+        self.arguments.jvm = [args.jvm, self.arguments.jvm].concat();
+        self.libraries = libraries;
+        self
+    }
 }
 
 impl NeoProfile {
@@ -51,42 +78,23 @@ impl NeoProfile {
         &self,
         name: impl AsRef<str>,
         side: Side,
-        lib_path: &PathBuf,
-        base_path: &PathBuf,
-    ) -> String {
-        let it = match side {
-            Side::Client => self
-                .data
-                .get(name.as_ref())
-                .unwrap()
-                .client
-                .clone()
-                .trim_start_matches('[')
-                .trim_end_matches(']')
-                .to_string(),
+        lib_path: &Path,
+        base_path: &Path,
+    ) -> Result<String> {
+        let name = name.as_ref();
 
-            Side::Server => self
-                .data
-                .get(name.as_ref())
-                .unwrap()
-                .server
-                .clone()
-                .trim_start_matches('[')
-                .trim_end_matches(']')
-                .to_string(),
+        let value = self
+            .data
+            .get(name)
+            .ok_or(anyhow!("Unknown install profile data key: {name}"))?;
+
+        let value = match side {
+            Side::Client => &value.client,
+            Side::Server => &value.server,
         };
 
-        if it.starts_with('/') {
-            base_path
-                .join(it.trim_start_matches('/'))
-                .to_str()
-                .unwrap()
-                .into()
-        } else if it.starts_with('\'') {
-            it
-        } else {
-            lib_path.join(maven_to_path(it)).to_str().unwrap().into()
-        }
+        resolve_data(value, lib_path, base_path)
+            .with_context(|| format!("Invalid value for install profile data key: {name}"))
     }
 
     pub fn add_minecraft(&mut self) {
@@ -113,10 +121,10 @@ impl NeoProfile {
 pub fn setup_vars(
     data: &NeoProfile,
     side: Side,
-    lib_path: &PathBuf,
-    base_path: &PathBuf,
-    jar_path: &PathBuf,
-) -> HashMap<String, String> {
+    lib_path: &Path,
+    base_path: &Path,
+    jar_path: &Path,
+) -> Result<HashMap<String, String>> {
     let mut vars = HashMap::<String, String>::new();
 
     vars.insert("INSTALLER".into(), jar_path.to_str().unwrap().into());
@@ -125,9 +133,9 @@ pub fn setup_vars(
     vars.insert("MINECRAFT_VERSION".into(), data.minecraft.clone());
     vars.insert("LIBRARY_DIR".into(), lib_path.to_str().unwrap().into());
 
-    for (key, _) in &data.data {
-        vars.insert(key.into(), data.data(key, side, lib_path, base_path));
+    for key in data.data.keys() {
+        vars.insert(key.into(), data.data(key, side, lib_path, base_path)?);
     }
 
-    vars
+    Ok(vars)
 }
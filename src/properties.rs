@@ -0,0 +1,267 @@
+use anyhow::Result;
+use std::{collections::HashSet, fmt::Write, fs, path::Path};
+
+/// Splits a `.properties` line into its raw key and value, or returns `None` for
+/// comments and blank lines.
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+
+    if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+        return None;
+    }
+
+    let mut escaped = false;
+    let mut split = line.len();
+
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            split = i;
+            break;
+        }
+    }
+
+    let rest = line[split..].trim_start();
+    let value = rest.strip_prefix(['=', ':']).unwrap_or(rest).trim_start();
+
+    Some((&line[..split], value))
+}
+
+/// Whether a line ends in an unescaped backslash, which continues it on the next line.
+fn continues(line: &str) -> bool {
+    line.chars().rev().take_while(|it| *it == '\\').count() % 2 == 1
+}
+
+/// Splits `.properties` content into logical lines, joining entries with their
+/// continuation lines. Each comes with the raw lines it was read from.
+fn logical_lines(content: &str) -> Vec<(String, Vec<&str>)> {
+    let mut out = Vec::new();
+    let mut lines = content.lines();
+
+    while let Some(first) = lines.next() {
+        let mut logical = first.to_string();
+        let mut raw = vec![first];
+        let is_entry = parse_line(first).is_some();
+
+        while is_entry && continues(&logical) {
+            let Some(next) = lines.next() else {
+                break;
+            };
+
+            logical.pop();
+            logical.push_str(next.trim_start());
+            raw.push(next);
+        }
+
+        out.push((logical, raw));
+    }
+
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::new();
+    let mut units = Vec::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+
+                if let Ok(unit) = u16::from_str_radix(&hex, 16) {
+                    units.push(unit);
+
+                    // Characters outside the BMP are written as two escaped surrogates.
+                    if let Ok(decoded) = String::from_utf16(&units) {
+                        out.push_str(&decoded);
+                        units.clear();
+                    }
+                }
+            }
+
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\x0c'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+
+    out
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::new();
+
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\x0c' => out.push_str("\\f"),
+            c if c.is_ascii() => out.push(c),
+
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    let _ = write!(out, "\\u{unit:04X}");
+                }
+            }
+        }
+    }
+
+    match out.starts_with(' ') {
+        true => format!("\\{out}"),
+        false => out,
+    }
+}
+
+/// Parses the entries of a `.properties` file, in order.
+pub fn parse_properties(content: &str) -> Vec<(String, String)> {
+    logical_lines(content)
+        .iter()
+        .filter_map(|(line, _)| parse_line(line))
+        .map(|(key, value)| (unescape(key), unescape(value)))
+        .collect()
+}
+
+/// Sets entries in a `.properties` file, keeping its other entries, comments and order.
+/// Later entries in `props` win, and new files start with `header`.
+pub fn merge_properties(path: &Path, header: &str, props: &[(String, String)]) -> Result<()> {
+    let existing = match path.exists() {
+        true => fs::read_to_string(path)?,
+        false => header.to_string(),
+    };
+
+    fs::write(path, merge(&existing, props))?;
+
+    Ok(())
+}
+
+/// Sets entries in `.properties` content. An entry that's continued over several lines is
+/// replaced as a whole.
+fn merge(existing: &str, props: &[(String, String)]) -> String {
+    let value = |key: &str| {
+        props
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+
+    let mut written = HashSet::new();
+    let mut lines = Vec::new();
+
+    for (line, raw) in logical_lines(existing) {
+        let key = parse_line(&line).map(|(key, _)| unescape(key));
+
+        match key.as_deref().and_then(|key| Some((key, value(key)?))) {
+            Some((key, value)) => {
+                lines.push(format!("{}={}", key, escape(value)));
+                written.insert(key.to_string());
+            }
+
+            None => lines.extend(raw.iter().map(|it| it.to_string())),
+        }
+    }
+
+    for (key, _) in props {
+        if written.insert(key.clone()) {
+            lines.push(format!("{key}={}", escape(value(key).unwrap_or_default())));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_comments_and_order() {
+        let existing = "#Minecraft server properties\n#Sat Nov 02 12:00:00 UTC 2024\nallow-flight=false\n\n! other comment\nmotd=A Minecraft Server\n";
+
+        assert_eq!(
+            merge(existing, &props(&[("allow-flight", "true")])),
+            "#Minecraft server properties\n#Sat Nov 02 12:00:00 UTC 2024\nallow-flight=true\n\n! other comment\nmotd=A Minecraft Server\n"
+        );
+    }
+
+    #[test]
+    fn replaces_and_adds_keys() {
+        let existing = "difficulty=easy\nmax-players=20\n";
+        let merged = merge(
+            existing,
+            &props(&[
+                ("max-players", "10"),
+                ("online-mode", "false"),
+                ("max-players", "5"),
+            ]),
+        );
+
+        assert_eq!(
+            merged,
+            "difficulty=easy\nmax-players=5\nonline-mode=false\n"
+        );
+    }
+
+    #[test]
+    fn replaces_continued_entries() {
+        let existing = "motd=hi\\\n  there\nlevel-name=world\n";
+
+        assert_eq!(
+            parse_properties(existing),
+            props(&[("motd", "hithere"), ("level-name", "world")])
+        );
+
+        assert_eq!(
+            merge(existing, &props(&[("motd", "new")])),
+            "motd=new\nlevel-name=world\n"
+        );
+
+        assert_eq!(
+            merge(existing, &props(&[("level-name", "other")])),
+            "motd=hi\\\n  there\nlevel-name=other\n"
+        );
+    }
+
+    #[test]
+    fn escaped_backslash_doesnt_continue() {
+        let existing = "path=C\\:\\\\\nmotd=hi\n";
+
+        assert_eq!(
+            parse_properties(existing),
+            props(&[("path", "C:\\"), ("motd", "hi")])
+        );
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        let existing = "motd=Caf\\u00E9 \\uD83D\\uDE00\n";
+
+        assert_eq!(parse_properties(existing), props(&[("motd", "Café 😀")]));
+
+        assert_eq!(
+            merge("", &props(&[("motd", "Café 😀")])),
+            "motd=Caf\\u00E9 \\uD83D\\uDE00\n"
+        );
+    }
+}
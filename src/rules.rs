@@ -0,0 +1,183 @@
+use regex::Regex;
+use std::{collections::HashMap, process::Command, sync::OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Allow,
+    Disallow,
+}
+
+/// A launcher rule, used by version JSONs to limit libraries and arguments to some
+/// platforms or launcher features.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub action: RuleAction,
+    pub os: Option<OsRule>,
+    pub features: Option<HashMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsRule {
+    pub name: Option<String>,
+    pub arch: Option<String>,
+    /// A regex matched against the OS version.
+    pub version: Option<String>,
+}
+
+/// The OS and architecture rules are evaluated against, named as in version JSONs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+    /// The OS version, as Java reports it in `os.version`, if it's known.
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "osx",
+            os => os,
+        };
+
+        let arch = match std::env::consts::ARCH {
+            "aarch64" => "arm64",
+            "arm" => "arm32",
+            arch => arch,
+        };
+
+        Self {
+            os: os.into(),
+            arch: arch.into(),
+            version: os_version().clone(),
+        }
+    }
+
+    /// The current platform, with the OS, architecture and/or OS version replaced. The
+    /// current OS version is only kept if the OS is.
+    pub fn with_overrides(
+        os: Option<String>,
+        arch: Option<String>,
+        version: Option<String>,
+    ) -> Self {
+        let current = Self::current();
+
+        let version = match &os {
+            Some(os) if *os != current.os => version,
+            _ => version.or(current.version),
+        };
+
+        Self {
+            os: os.unwrap_or(current.os),
+            arch: arch.unwrap_or(current.arch),
+            version,
+        }
+    }
+
+    /// The pointer width, as substituted for `${arch}` in native classifiers.
+    pub fn bits(&self) -> &'static str {
+        match self.arch.as_str() {
+            "x86" | "arm32" => "32",
+            _ => "64",
+        }
+    }
+}
+
+/// The OS version as Java reports it: the product version on macOS, `major.minor` on
+/// Windows and the kernel release elsewhere.
+fn os_version() -> &'static Option<String> {
+    static VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+    VERSION.get_or_init(|| {
+        let (program, args) = match std::env::consts::OS {
+            "macos" => ("sw_vers", ["-productVersion"].as_slice()),
+            "windows" => ("cmd", ["/c", "ver"].as_slice()),
+            _ => ("uname", ["-r"].as_slice()),
+        };
+
+        let output = Command::new(program).args(args).output().ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+
+        // `ver` prints something like "Microsoft Windows [Version 10.0.19045.3803]".
+        let version = match output.split_once("Version ") {
+            Some((_, version)) => version.split('.').take(2).collect::<Vec<_>>().join("."),
+
+            None => output.trim().to_string(),
+        };
+
+        Some(version).filter(|it| !it.is_empty())
+    })
+}
+
+/// Launcher features that argument rules can depend on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Features {
+    pub is_demo_user: bool,
+    pub has_custom_resolution: bool,
+    pub has_quick_plays_support: bool,
+    pub is_quick_play_singleplayer: bool,
+    pub is_quick_play_multiplayer: bool,
+    pub is_quick_play_realms: bool,
+}
+
+impl Features {
+    /// Whether a feature is enabled, by its name in version JSONs. Unknown features are
+    /// never enabled.
+    pub fn enabled(&self, name: &str) -> bool {
+        match name {
+            "is_demo_user" => self.is_demo_user,
+            "has_custom_resolution" => self.has_custom_resolution,
+            "has_quick_plays_support" => self.has_quick_plays_support,
+            "is_quick_play_singleplayer" => self.is_quick_play_singleplayer,
+            "is_quick_play_multiplayer" => self.is_quick_play_multiplayer,
+            "is_quick_play_realms" => self.is_quick_play_realms,
+            _ => false,
+        }
+    }
+}
+
+impl OsRule {
+    pub fn matches(&self, platform: &Platform) -> bool {
+        self.name.as_ref().is_none_or(|it| *it == platform.os)
+            && self.arch.as_ref().is_none_or(|it| *it == platform.arch)
+            && self.version.as_ref().is_none_or(|pattern| {
+                let version = platform.version.as_deref();
+
+                Regex::new(pattern).is_ok_and(|it| version.is_some_and(|v| it.is_match(v)))
+            })
+    }
+}
+
+impl Rule {
+    pub fn matches(&self, platform: &Platform, features: &Features) -> bool {
+        self.os.as_ref().is_none_or(|it| it.matches(platform))
+            && self.features.as_ref().is_none_or(|it| {
+                it.iter()
+                    .all(|(name, enabled)| features.enabled(name) == *enabled)
+            })
+    }
+}
+
+/// Evaluates a list of rules the way the launcher does: with no rules everything is
+/// allowed, otherwise the last rule that applies decides.
+pub fn rules_allow(rules: &[Rule], platform: &Platform, features: &Features) -> bool {
+    if rules.is_empty() {
+        return true;
+    }
+
+    rules
+        .iter()
+        .rfind(|it| it.matches(platform, features))
+        .is_some_and(|it| it.action == RuleAction::Allow)
+}
@@ -0,0 +1,343 @@
+use crate::{
+    download::{Download, download_all, sha1_hex},
+    java::{JAVA_EXE, JavaRuntime, probe},
+    rules::Platform,
+};
+use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Mojang's index of the Java runtimes its launcher downloads.
+pub const JAVA_RUNTIME_URL: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// The file in a runtime's directory that records the manifest it was installed from.
+const INSTALLED_MARKER: &str = ".manifest-sha1";
+
+/// Maps a platform name in the runtime index to its components' releases.
+pub type RuntimeIndex = HashMap<String, HashMap<String, Vec<RuntimeRelease>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeRelease {
+    pub manifest: RuntimeDownload,
+    pub version: RuntimeVersion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeVersion {
+    pub name: String,
+    pub released: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDownload {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeManifest {
+    /// Maps each path in the runtime to what's there.
+    pub files: BTreeMap<String, RuntimeFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum RuntimeFile {
+    Directory,
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Link {
+        target: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeFileDownloads {
+    pub raw: RuntimeDownload,
+    pub lzma: Option<RuntimeDownload>,
+}
+
+/// The name the runtime index uses for a platform, if Mojang ships runtimes for it.
+pub fn runtime_platform(platform: &Platform) -> Option<&'static str> {
+    match (platform.os.as_str(), platform.arch.as_str()) {
+        ("linux", "x86_64") => Some("linux"),
+        ("linux", "x86") => Some("linux-i386"),
+        ("osx", "x86_64") => Some("mac-os"),
+        ("osx", "arm64") => Some("mac-os-arm64"),
+        ("windows", "x86_64") => Some("windows-x64"),
+        ("windows", "x86") => Some("windows-x86"),
+        ("windows", "arm64") => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+/// Where downloaded runtimes are shared between installs by default, in the user's data
+/// directory.
+pub fn default_runtimes_dir() -> Result<PathBuf> {
+    let data_dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|it| PathBuf::from(it).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|it| PathBuf::from(it).join(".local/share")))
+    };
+
+    data_dir
+        .map(|it| it.join("neo-install").join("runtimes"))
+        .ok_or(anyhow!(
+            "Couldn't find a directory for Java runtimes, pass one with --runtimes-dir!"
+        ))
+}
+
+/// The Java executable inside a runtime, which is in an app bundle on macOS.
+fn runtime_java(dir: &Path) -> Option<PathBuf> {
+    [
+        dir.join("bin").join(JAVA_EXE),
+        dir.join("jre.bundle/Contents/Home/bin").join(JAVA_EXE),
+    ]
+    .into_iter()
+    .find(|it| it.is_file())
+}
+
+/// Resolves `..` and `.` in a relative path without touching the filesystem, or returns
+/// `None` if it's absolute or climbs out of the directory it's relative to.
+fn contained(path: &Path) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(it) => out.push(it),
+            Component::CurDir => {}
+            Component::ParentDir if out.pop() => {}
+            _ => return None,
+        }
+    }
+
+    Some(out)
+}
+
+/// Checks that every file in a runtime manifest stays inside the runtime's directory, and
+/// that links only point at other files in it. The index can be a stand-in for Mojang's, so
+/// a manifest could otherwise write or link anywhere.
+fn check_manifest(manifest: &RuntimeManifest) -> Result<()> {
+    for (name, file) in &manifest.files {
+        let path = Path::new(name);
+
+        if path.components().any(|it| it == Component::ParentDir)
+            || contained(path).is_none_or(|it| it.as_os_str().is_empty())
+        {
+            return Err(anyhow!("Invalid path in the Java runtime manifest: {name}"));
+        }
+
+        if let RuntimeFile::Link { target } = file
+            && (Path::new(target).is_absolute()
+                || contained(&path.parent().unwrap_or(Path::new("")).join(target)).is_none())
+        {
+            return Err(anyhow!(
+                "Link out of the Java runtime in its manifest: {name} -> {target}"
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn fetch_manifest(release: &RuntimeRelease) -> Result<RuntimeManifest> {
+    let data = reqwest::get(&release.manifest.url)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    if !release.manifest.sha1.eq_ignore_ascii_case(&sha1_hex(&data)) {
+        return Err(anyhow!(
+            "Hash mismatch for Java runtime manifest: {}",
+            release.manifest.url
+        ));
+    }
+
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Downloads a Java runtime component (like `java-runtime-delta`) for a platform into
+/// `runtimes_dir/<component>/<platform>`, unless it's already there.
+pub async fn install_runtime(
+    component: &str,
+    index_url: &str,
+    runtimes_dir: &Path,
+    platform: &Platform,
+) -> Result<JavaRuntime> {
+    let key = runtime_platform(platform).ok_or(anyhow!(
+        "Mojang doesn't provide Java runtimes for {} ({})!",
+        platform.os,
+        platform.arch
+    ))?;
+
+    let index = reqwest::get(index_url)
+        .await?
+        .error_for_status()?
+        .json::<RuntimeIndex>()
+        .await
+        .with_context(|| format!("Failed to read the Java runtime index: {index_url}"))?;
+
+    let release = index
+        .get(key)
+        .and_then(|it| it.get(component))
+        .and_then(|it| it.first())
+        .ok_or(anyhow!(
+            "No {component} Java runtime is available for {key}!"
+        ))?;
+
+    let dir = runtimes_dir.join(component).join(key);
+    let marker = dir.join(INSTALLED_MARKER);
+
+    let installed = fs::read_to_string(&marker).is_ok_and(|it| it.trim() == release.manifest.sha1);
+
+    if let Some(java) = runtime_java(&dir).filter(|_| installed) {
+        return probe(&java);
+    }
+
+    println!(
+        "Downloading Java runtime {component} {} into {}...",
+        release.version.name,
+        dir.display()
+    );
+
+    let manifest = fetch_manifest(release).await?;
+
+    check_manifest(&manifest)?;
+
+    let mut downloads = Vec::new();
+
+    for (name, file) in &manifest.files {
+        let path = dir.join(name);
+
+        match file {
+            RuntimeFile::Directory => fs::create_dir_all(&path)?,
+
+            RuntimeFile::File { downloads: it, .. } => downloads.push(Download {
+                path,
+                urls: vec![it.raw.url.clone()],
+                sha1: Some(it.raw.sha1.clone()),
+            }),
+
+            RuntimeFile::Link { .. } => {}
+        }
+    }
+
+    let failed = download_all(&downloads)?;
+
+    if let Some((download, err)) = failed.first() {
+        return Err(anyhow!(
+            "Failed to download {} of the Java runtime ({} files failed): {err}",
+            download.path.display(),
+            failed.len()
+        ));
+    }
+
+    for (name, file) in &manifest.files {
+        let path = dir.join(name);
+
+        match file {
+            #[cfg(unix)]
+            RuntimeFile::File {
+                executable: true, ..
+            } => {
+                use std::os::unix::fs::PermissionsExt;
+
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+            }
+
+            #[cfg(unix)]
+            RuntimeFile::Link { target } => {
+                if path.symlink_metadata().is_ok() {
+                    fs::remove_file(&path)?;
+                }
+
+                fs::create_dir_all(path.parent().unwrap())?;
+                std::os::unix::fs::symlink(target, &path)?;
+            }
+
+            // Windows runtimes don't use links, and don't need executable bits.
+            _ => {}
+        }
+    }
+
+    fs::write(&marker, &release.manifest.sha1)?;
+
+    let java = runtime_java(&dir).ok_or(anyhow!(
+        "The {component} Java runtime doesn't contain a Java executable!"
+    ))?;
+
+    probe(&java)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(files: &[(&str, RuntimeFile)]) -> RuntimeManifest {
+        RuntimeManifest {
+            files: files
+                .iter()
+                .map(|(name, file)| (name.to_string(), file.clone()))
+                .collect(),
+        }
+    }
+
+    fn link(target: &str) -> RuntimeFile {
+        RuntimeFile::Link {
+            target: target.into(),
+        }
+    }
+
+    #[test]
+    fn accepts_runtime_layout() {
+        assert!(
+            check_manifest(&manifest(&[
+                ("bin", RuntimeFile::Directory),
+                ("jre.bundle/Contents/Home", RuntimeFile::Directory),
+                (
+                    "jre.bundle/Contents/MacOS/libjli.dylib",
+                    link("../Home/lib/libjli.dylib")
+                ),
+                ("legal/java.base/LICENSE", link("./ADDITIONAL_LICENSE_INFO")),
+            ]))
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn rejects_escaping_paths() {
+        for name in ["../../.bashrc", "/etc/passwd", "bin/../../x", ""] {
+            assert!(
+                check_manifest(&manifest(&[(name, RuntimeFile::Directory)])).is_err(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_escaping_links() {
+        for target in ["/etc", "../../..", "../lib/../../x"] {
+            assert!(
+                check_manifest(&manifest(&[("bin/java", link(target))])).is_err(),
+                "{target}"
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use std::{path::PathBuf, process::Command};
+
+#[cfg(target_os = "linux")]
+use landlock::{
+    ABI, Access, AccessFs, AccessNet, RulesetAttr, RulesetCreated, RulesetCreatedAttr,
+    RulesetStatus, path_beneath_rules,
+};
+
+/// System directories processors may read from when sandboxed.
+pub const SYSTEM_READ_PATHS: &[&str] = &[
+    "/usr",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/bin",
+    "/sbin",
+    "/etc",
+    "/opt",
+    "/proc",
+    "/sys",
+    "/nix/store",
+];
+
+/// Which paths a sandboxed processor may access.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxPolicy {
+    pub read: Vec<PathBuf>,
+    pub write: Vec<PathBuf>,
+}
+
+/// A Linux sandbox for a child process: a new user and network namespace (so there is no
+/// network access) and a Landlock ruleset limiting filesystem access to a [`SandboxPolicy`].
+pub struct Sandbox {
+    #[cfg(target_os = "linux")]
+    ruleset: Option<RulesetCreated>,
+    #[cfg(target_os = "linux")]
+    uid_map: Vec<u8>,
+    #[cfg(target_os = "linux")]
+    gid_map: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl Sandbox {
+    pub fn new(policy: &SandboxPolicy) -> Result<Self> {
+        let fs_abi = ABI::V2;
+        let read = AccessFs::from_read(fs_abi);
+
+        let ruleset = landlock::Ruleset::default()
+            .handle_access(AccessFs::from_all(fs_abi))?
+            .handle_access(AccessNet::from_all(ABI::V4))?
+            .create()?
+            .add_rules(path_beneath_rules(SYSTEM_READ_PATHS, read))?
+            .add_rules(path_beneath_rules(["/dev"], read | AccessFs::WriteFile))?
+            .add_rules(path_beneath_rules(&policy.read, read))?
+            .add_rules(path_beneath_rules(
+                &policy.write,
+                AccessFs::from_all(fs_abi),
+            ))?;
+
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+
+        Ok(Self {
+            ruleset: Some(ruleset),
+            uid_map: format!("{uid} {uid} 1").into_bytes(),
+            gid_map: format!("{gid} {gid} 1").into_bytes(),
+        })
+    }
+
+    pub fn apply(mut self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        // This runs between fork and exec, so it sticks to raw syscalls and avoids allocating.
+        let pre_exec = move || -> std::io::Result<()> {
+            if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            write_proc(c"/proc/self/setgroups", b"deny")?;
+            write_proc(c"/proc/self/uid_map", &self.uid_map)?;
+            write_proc(c"/proc/self/gid_map", &self.gid_map)?;
+
+            let Some(ruleset) = self.ruleset.take() else {
+                return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+            };
+
+            match ruleset.restrict_self() {
+                Ok(status) if status.ruleset != RulesetStatus::NotEnforced => Ok(()),
+                _ => Err(std::io::Error::from_raw_os_error(libc::ENOSYS)),
+            }
+        };
+
+        unsafe {
+            cmd.pre_exec(pre_exec);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_proc(path: &std::ffi::CStr, content: &[u8]) -> std::io::Result<()> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let written = unsafe { libc::write(fd, content.as_ptr().cast(), content.len()) };
+
+    unsafe {
+        libc::close(fd);
+    }
+
+    match written {
+        n if n < 0 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Sandbox {
+    pub fn new(_policy: &SandboxPolicy) -> Result<Self> {
+        Err(anyhow!("Sandboxing processors is only supported on Linux!"))
+    }
+
+    pub fn apply(self, _cmd: &mut Command) {}
+}
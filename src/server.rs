@@ -0,0 +1,553 @@
+use crate::{
+    argfile::parse_argfile,
+    install::fetch_installer,
+    java::JavaOptions,
+    manifest::fetch_version_manifest,
+    properties::{merge_properties, parse_properties},
+    signal::interrupted,
+    util::clean_installer_dir,
+};
+use anyhow::{Context, Result};
+use clap::Args;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use zip::ZipArchive;
+
+/// Marks the start of the part of a generated file that neo-install manages.
+pub const MANAGED_START: &str = ">>> neo-install >>>";
+/// Marks the end of the part of a generated file that neo-install manages.
+pub const MANAGED_END: &str = "<<< neo-install <<<";
+
+/// Where processors put each NeoForge version's server argument files, relative to the
+/// server directory.
+pub const NEOFORGE_LIBRARY_DIR: &str = "libraries/net/neoforged/neoforge";
+
+/// The argument file the server is started with on this OS.
+#[cfg(windows)]
+pub const SERVER_ARGS_FILE: &str = "win_args.txt";
+
+/// The argument file the server is started with on this OS.
+#[cfg(not(windows))]
+pub const SERVER_ARGS_FILE: &str = "unix_args.txt";
+
+/// Settings for the generated server start scripts.
+#[derive(Debug, Clone, Default, Args, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ServerOptions {
+    /// The maximum heap size for the server, written to user_jvm_args.txt (e.g. 4G).
+    #[clap(long = "server-memory", value_name = "SIZE")]
+    pub memory: Option<String>,
+
+    /// Extra JVM arguments for the server, written to user_jvm_args.txt.
+    #[clap(
+        id = "server_jvm_args",
+        long = "server-jvm-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true
+    )]
+    pub jvm_args: Vec<String>,
+
+    /// Make the start scripts run the server without its GUI.
+    #[clap(long = "nogui")]
+    pub nogui: bool,
+
+    /// Accept the Minecraft EULA (https://aka.ms/MinecraftEULA) by writing eula.txt.
+    #[clap(long = "accept-eula")]
+    pub accept_eula: bool,
+
+    /// A property to set in server.properties.
+    #[clap(long = "property", value_name = "KEY=VALUE")]
+    pub properties: Vec<String>,
+
+    /// A properties file to merge into server.properties, before any `--property`.
+    #[clap(long = "properties-file", value_name = "PATH")]
+    pub properties_file: Option<PathBuf>,
+}
+
+impl ServerOptions {
+    /// Merges two sets of options, with `other` taking priority.
+    pub fn merge(self, other: ServerOptions) -> Self {
+        Self {
+            memory: other.memory.or(self.memory),
+            jvm_args: [self.jvm_args, other.jvm_args].concat(),
+            nogui: self.nogui || other.nogui,
+            accept_eula: self.accept_eula || other.accept_eula,
+            properties: [self.properties, other.properties].concat(),
+            properties_file: other.properties_file.or(self.properties_file),
+        }
+    }
+
+    /// The entries to set in server.properties, from the properties file and then
+    /// `--property`.
+    pub fn properties(&self) -> Result<Vec<(String, String)>> {
+        let mut properties = match &self.properties_file {
+            Some(path) => parse_properties(
+                &fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            ),
+
+            None => Vec::new(),
+        };
+
+        for it in &self.properties {
+            let (key, value) = it
+                .split_once('=')
+                .ok_or(anyhow!("Properties must be KEY=VALUE: {it}"))?;
+
+            properties.push((key.to_string(), value.to_string()));
+        }
+
+        Ok(properties)
+    }
+}
+
+/// The start scripts written for server installs.
+pub const SERVER_SCRIPTS: &[&str] = &["run.sh", "run.bat"];
+
+/// Reads the start scripts a server directory already has, so they can be put back after
+/// processors overwrite them with the installer's copies.
+pub fn save_scripts(work_dir: &Path) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut saved = Vec::new();
+
+    for name in SERVER_SCRIPTS {
+        let path = work_dir.join(name);
+
+        match fs::read(&path) {
+            Ok(content) => saved.push((path, content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        }
+    }
+
+    Ok(saved)
+}
+
+/// Puts back the start scripts read by [`save_scripts`].
+pub fn restore_scripts(saved: &[(PathBuf, Vec<u8>)]) -> Result<()> {
+    for (path, content) in saved {
+        fs::write(path, content)
+            .with_context(|| format!("Failed to restore {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Removes a start script that's still the installer's own copy from `data/`, so it gets
+/// replaced with one that has a managed block.
+fn remove_installer_script(path: &Path, installer_copy: &Path) -> Result<()> {
+    let normalize = |it: Vec<u8>| String::from_utf8_lossy(&it).replace("\r\n", "\n");
+
+    if let (Ok(script), Ok(original)) = (fs::read(path), fs::read(installer_copy))
+        && normalize(script) == normalize(original)
+    {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `content` into the managed block of a file, between [`MANAGED_START`] and
+/// [`MANAGED_END`] comments. Everything outside the block is left for the user to edit.
+/// New files get the block between `header` and `footer`. Files without a block get it
+/// appended if `append` is set, and are left alone otherwise. Returns whether the block
+/// was written.
+fn write_managed(
+    path: &Path,
+    (header, footer): (&str, &str),
+    comment: &str,
+    content: &str,
+    eol: &str,
+    append: bool,
+) -> Result<bool> {
+    let start = format!("{comment} {MANAGED_START}");
+    let end = format!("{comment} {MANAGED_END}");
+    let block = format!("{start}{eol}{content}{end}{eol}");
+
+    if !path.exists() {
+        fs::write(path, format!("{header}{block}{footer}"))?;
+        return Ok(true);
+    }
+
+    let existing = fs::read_to_string(path)?;
+
+    let (Some(block_start), Some(block_end)) = (existing.find(&start), existing.find(&end)) else {
+        if !append {
+            return Ok(false);
+        }
+
+        let separator = match existing.is_empty() || existing.ends_with('\n') {
+            true => "",
+            false => eol,
+        };
+
+        fs::write(path, format!("{existing}{separator}{block}"))?;
+        return Ok(true);
+    };
+
+    let block_end = existing[block_end..]
+        .find('\n')
+        .map(|it| block_end + it + 1)
+        .unwrap_or(existing.len());
+
+    fs::write(
+        path,
+        format!(
+            "{}{block}{}",
+            &existing[..block_start],
+            &existing[block_end..]
+        ),
+    )?;
+
+    Ok(true)
+}
+
+fn lines(lines: &[String], eol: &str) -> String {
+    lines.iter().map(|it| format!("{it}{eol}")).collect()
+}
+
+/// Writes `run.sh`, `run.bat` and `user_jvm_args.txt` for a server install, matching what
+/// NeoForge's installer generates. The argument files they point at are copied from the
+/// installer's data if processors didn't write them.
+///
+/// Only the generated settings go in managed blocks, which are rewritten on every install:
+/// the argument file and `nogui` in the scripts, and `--server-memory` and
+/// `--server-jvm-arg` in `user_jvm_args.txt`. The rest is only written when a file is
+/// created, so the user's edits are kept. Scripts that are still the installer's own copies
+/// are replaced.
+pub fn write_server_files(
+    work_dir: &Path,
+    neo: &str,
+    base_path: &Path,
+    opts: &ServerOptions,
+) -> Result<()> {
+    let args_dir = format!("{NEOFORGE_LIBRARY_DIR}/{neo}");
+
+    for file in ["unix_args.txt", "win_args.txt"] {
+        let src = base_path.join("data").join(file);
+        let dest = work_dir.join(&args_dir).join(file);
+
+        if src.exists() && !dest.exists() {
+            fs::create_dir_all(dest.parent().unwrap())?;
+            fs::copy(src, dest)?;
+        }
+    }
+
+    let nogui = if opts.nogui { " nogui" } else { "" };
+
+    let template = lines(
+        &[
+            "# Xmx and Xms set the maximum and minimum RAM usage, respectively.".into(),
+            "# They can take any number, followed by an M or a G.".into(),
+            "# M means Megabyte, G means Gigabyte.".into(),
+            "# For example, to set the maximum to 3GB: -Xmx3G".into(),
+            "# To set the minimum to 2.5GB: -Xms2500M".into(),
+            "".into(),
+            "# A good default for a modern server is to set min and max to the same number,".into(),
+            "# though it can also be fine to leave the minimum unset.".into(),
+            "".into(),
+            "# Uncomment the next line to set it.".into(),
+            "# -Xmx4G".into(),
+            "".into(),
+            "# The settings below are rewritten by neo-install's --server-memory and".into(),
+            "# --server-jvm-arg on every install. They come last, so they win.".into(),
+        ],
+        "\n",
+    );
+
+    let jvm_args = opts
+        .memory
+        .iter()
+        .map(|it| format!("-Xmx{it}"))
+        .chain(opts.jvm_args.iter().cloned())
+        .collect::<Vec<_>>();
+
+    let user_jvm_args = work_dir.join("user_jvm_args.txt");
+
+    // Files without a block only get one if there's something to put in it.
+    write_managed(
+        &user_jvm_args,
+        (&template, ""),
+        "#",
+        &lines(&jvm_args, "\n"),
+        "\n",
+        !jvm_args.is_empty(),
+    )?;
+
+    let run_sh = work_dir.join("run.sh");
+
+    remove_installer_script(&run_sh, &base_path.join("data").join("run.sh"))?;
+
+    let written = write_managed(
+        &run_sh,
+        (
+            &lines(
+                &[
+                    "#!/usr/bin/env sh".into(),
+                    "# NeoForge requires a configured set of both JVM and program arguments.".into(),
+                    "# Add custom JVM arguments to the user_jvm_args.txt".into(),
+                    "# Add custom program arguments {such as nogui} to the java line below, before the \"$@\" or".into(),
+                    "#  pass them to this script directly".into(),
+                ],
+                "\n",
+            ),
+            "java @user_jvm_args.txt $NEOFORGE_ARGS \"$@\"\n",
+        ),
+        "#",
+        &format!("NEOFORGE_ARGS=\"@{args_dir}/unix_args.txt{nogui}\"\n"),
+        "\n",
+        false,
+    )?;
+
+    if !written {
+        warn_unmanaged(&run_sh, &format!("@{args_dir}/unix_args.txt{nogui}"));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&run_sh, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let run_bat = work_dir.join("run.bat");
+
+    remove_installer_script(&run_bat, &base_path.join("data").join("run.bat"))?;
+
+    let written = write_managed(
+        &run_bat,
+        (
+            &lines(
+                &[
+                    "@echo off".into(),
+                    "REM NeoForge requires a configured set of both JVM and program arguments.".into(),
+                    "REM Add custom JVM arguments to the user_jvm_args.txt".into(),
+                    "REM Add custom program arguments {such as nogui} to the java line below, before the %* or".into(),
+                    "REM  pass them to this script directly".into(),
+                ],
+                "\r\n",
+            ),
+            "java @user_jvm_args.txt %NEOFORGE_ARGS% %*\r\npause\r\n",
+        ),
+        "REM",
+        &format!("set NEOFORGE_ARGS=@{args_dir}/win_args.txt{nogui}\r\n"),
+        "\r\n",
+        false,
+    )?;
+
+    if !written {
+        warn_unmanaged(&run_bat, &format!("@{args_dir}/win_args.txt{nogui}"));
+    }
+
+    Ok(())
+}
+
+/// Warns that a start script wasn't updated because neo-install didn't write it.
+fn warn_unmanaged(path: &Path, args: &str) {
+    eprintln!(
+        "Warning: {} wasn't generated by neo-install, so it was left alone. Make sure it starts the server with {args}.",
+        path.display()
+    );
+}
+
+/// Writes `eula.txt` if the EULA was accepted and merges the configured properties into
+/// `server.properties`, leaving any other keys alone.
+pub fn write_server_config(work_dir: &Path, opts: &ServerOptions) -> Result<()> {
+    if opts.accept_eula {
+        merge_properties(
+            &work_dir.join("eula.txt"),
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA (https://aka.ms/MinecraftEULA).\n",
+            &[("eula".into(), "true".into())],
+        )?;
+    }
+
+    let properties = opts.properties()?;
+
+    if !properties.is_empty() {
+        merge_properties(
+            &work_dir.join("server.properties"),
+            "#Minecraft server properties\n",
+            &properties,
+        )?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub dir: PathBuf,
+    pub neo: Option<String>,
+    pub java: JavaOptions,
+    pub nogui: bool,
+    pub args: Vec<String>,
+}
+
+/// Finds the NeoForge version installed in a server directory from its argument files.
+pub fn installed_version(dir: &Path) -> Result<String> {
+    let versions = fs::read_dir(dir.join(NEOFORGE_LIBRARY_DIR))
+        .into_iter()
+        .flatten()
+        .filter_map(|it| it.ok())
+        .filter(|it| it.path().join(SERVER_ARGS_FILE).exists())
+        .map(|it| it.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    match versions.as_slice() {
+        [version] => Ok(version.clone()),
+        [] => Err(anyhow!(
+            "No NeoForge server is installed in {}!",
+            dir.display()
+        )),
+
+        _ => Err(anyhow!(
+            "Multiple NeoForge versions are installed in {} ({}), pick one with --neo!",
+            dir.display(),
+            versions.join(", ")
+        )),
+    }
+}
+
+/// Reads a server argument file from the installer's data, for servers whose processors
+/// didn't write one.
+async fn installer_args_file(neo: &str, base_path: &Path) -> Result<String> {
+    let created = !base_path.exists();
+    let installer = fetch_installer(neo, base_path).await?;
+    let mut zip = ZipArchive::new(File::open(&installer.jar_path)?)?;
+    let mut content = String::new();
+
+    zip.by_name(&format!("data/{SERVER_ARGS_FILE}"))
+        .with_context(|| format!("The NeoForge {neo} installer has no {SERVER_ARGS_FILE}"))?
+        .read_to_string(&mut content)?;
+
+    if created {
+        clean_installer_dir(base_path, false, true)?;
+    }
+
+    Ok(content)
+}
+
+/// The arguments `java` is run with to start an installed server: the user's
+/// `user_jvm_args.txt`, then the version's server argument file.
+pub async fn server_args(dir: &Path, neo: Option<&str>, nogui: bool) -> Result<Vec<String>> {
+    let neo = match neo {
+        Some(neo) => neo.to_string(),
+        None => installed_version(dir)?,
+    };
+
+    let args_file = dir
+        .join(NEOFORGE_LIBRARY_DIR)
+        .join(&neo)
+        .join(SERVER_ARGS_FILE);
+
+    let server_args = match args_file.exists() {
+        true => fs::read_to_string(&args_file)?,
+
+        false => {
+            println!(
+                "{} is missing, reading it from the installer instead.",
+                args_file.display()
+            );
+
+            installer_args_file(&neo, &dir.join(".installer")).await?
+        }
+    };
+
+    let user_args = match dir.join("user_jvm_args.txt") {
+        path if path.exists() => fs::read_to_string(path)?,
+        _ => String::new(),
+    };
+
+    let mut args = [parse_argfile(&user_args), parse_argfile(&server_args)].concat();
+
+    if nogui {
+        args.push("nogui".into());
+    }
+
+    Ok(args)
+}
+
+/// Picks the Java executable to run an installed server with, checked against the Java
+/// version its Minecraft version needs. That's looked up online, so the check is skipped
+/// if it can't be.
+pub async fn server_java(java: &JavaOptions, args: &[String]) -> Result<String> {
+    let minecraft = args.iter().skip_while(|it| *it != "--fml.mcVersion").nth(1);
+
+    let required = match minecraft {
+        Some(minecraft) => match fetch_version_manifest(minecraft).await {
+            Ok(manifest) => manifest.java_version,
+
+            Err(err) => {
+                eprintln!("Couldn't check which Java version Minecraft {minecraft} needs: {err}");
+                None
+            }
+        },
+
+        None => None,
+    };
+
+    java.resolve(required.as_ref()).await
+}
+
+/// Runs an installed server in the foreground with its console attached. SIGINT and
+/// SIGTERM are turned into a `stop` command so the world gets saved.
+pub async fn run_server(opts: &RunOptions) -> Result<()> {
+    let args = server_args(&opts.dir, opts.neo.as_deref(), opts.nogui).await?;
+    let java = server_java(&opts.java, &args).await?;
+
+    let mut child = Command::new(&java)
+        .args(args)
+        .args(&opts.args)
+        .current_dir(&opts.dir)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start Java: {java}"))?;
+
+    let stdin = Arc::new(Mutex::new(child.stdin.take().unwrap()));
+
+    {
+        let stdin = stdin.clone();
+
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if writeln!(stdin.lock().unwrap(), "{line}").is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    let mut stopping = false;
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        if interrupted() && !stopping {
+            stopping = true;
+
+            // The server may have already closed its console if it got the signal too.
+            let _ = writeln!(stdin.lock().unwrap(), "stop");
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("The server exited with {status}")),
+    }
+}
@@ -9,6 +9,15 @@ pub enum Side {
     Client,
 }
 
+/// The side(s) to install.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InstallSide {
+    Client,
+    Server,
+    /// Both sides, into `client` and `server` subdirectories of the target.
+    Both,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Sided<T> {
@@ -24,3 +33,13 @@ impl Side {
         }
     }
 }
+
+impl InstallSide {
+    pub fn sides(&self) -> &'static [Side] {
+        match *self {
+            Self::Client => &[Side::Client],
+            Self::Server => &[Side::Server],
+            Self::Both => &[Side::Client, Side::Server],
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use std::{
+    process::{Child, ExitStatus},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a child gets to exit after being asked to terminate before it is killed.
+pub const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Installs a SIGINT/SIGTERM handler that flags the current operation as interrupted.
+/// A second signal exits immediately.
+pub fn install_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("Interrupted again, exiting immediately!");
+            std::process::exit(130);
+        }
+
+        eprintln!("Interrupted, stopping...");
+    })?;
+
+    Ok(())
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub fn check_interrupted() -> Result<()> {
+    match interrupted() {
+        true => Err(anyhow!("Installation was interrupted!")),
+        false => Ok(()),
+    }
+}
+
+/// Asks a child process to terminate (SIGTERM on Unix), killing it if it hasn't exited
+/// within [`TERMINATE_GRACE_PERIOD`].
+pub fn terminate_child(child: &mut Child) -> Result<ExitStatus> {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+
+    #[cfg(not(unix))]
+    child.kill()?;
+
+    let start = Instant::now();
+
+    while start.elapsed() < TERMINATE_GRACE_PERIOD {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    child.kill()?;
+
+    Ok(child.wait()?)
+}
@@ -0,0 +1,399 @@
+use crate::{
+    install::{InstallOptions, install},
+    junit::{TestCase, TestSuite},
+    server::{
+        NEOFORGE_LIBRARY_DIR, SERVER_ARGS_FILE, server_args, server_java, write_server_config,
+    },
+    signal::{interrupted, terminate_child},
+};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How long the server gets to shut down after being sent `stop`.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ReportFormat {
+    /// The full report as JSON.
+    #[default]
+    Json,
+    /// A JUnit XML test suite.
+    Junit,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmokeTestOptions {
+    pub install: InstallOptions,
+    pub startup_timeout: Duration,
+    pub reinstall: bool,
+    pub report: Option<PathBuf>,
+    pub report_format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmokeTestOutcome {
+    /// The server started and stopped cleanly.
+    Passed,
+    /// The server exited before it finished starting.
+    Crashed,
+    /// The server didn't finish starting in time.
+    TimedOut,
+    /// The server started, but didn't stop cleanly.
+    StopFailed,
+    /// The server couldn't be installed or started.
+    SetupFailed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmokeTestReport {
+    pub neo: String,
+    pub passed: bool,
+    pub outcome: SmokeTestOutcome,
+    pub message: String,
+    /// How long the server took to start, in seconds.
+    pub startup_time: Option<f64>,
+    /// How long the whole run took, including installing the server, in seconds.
+    pub duration: f64,
+    pub exit_code: Option<i32>,
+    pub crash_report: Option<PathBuf>,
+}
+
+impl SmokeTestReport {
+    pub fn to_junit(&self) -> TestSuite {
+        TestSuite {
+            name: "neo-install smoke test".into(),
+            cases: vec![TestCase {
+                class: "neoforge.server".into(),
+                name: format!("NeoForge {} server starts and stops", self.neo),
+                time: self.duration,
+                failure: (!self.passed).then(|| self.message.clone()),
+                skipped: None,
+                output: self
+                    .crash_report
+                    .as_ref()
+                    .map(|it| format!("Crash report: {}", it.display())),
+            }],
+        }
+    }
+
+    /// Writes the report to the path in `opts`, if there is one.
+    fn write_to(&self, opts: &SmokeTestOptions) -> Result<()> {
+        match &opts.report {
+            Some(path) => self.write(path, opts.report_format),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write(&self, path: &Path, format: ReportFormat) -> Result<()> {
+        match format {
+            ReportFormat::Junit => self.to_junit().write(path)?,
+
+            ReportFormat::Json => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                fs::write(path, serde_json::to_string_pretty(self)?)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Starts a server with its console piped. Every line it prints is echoed to stdout and
+/// sent to the returned channel, which disconnects once the server closes its output.
+pub fn spawn_server(dir: &Path, java: &str, args: &[String]) -> Result<(Child, Receiver<String>)> {
+    let mut child = Command::new(java)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start Java: {java}"))?;
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(|it| it.ok()) {
+            println!("{line}");
+
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok((child, rx))
+}
+
+/// Sends a command to a server's console.
+pub fn send_command(child: &mut Child, command: &str) -> Result<()> {
+    let stdin = child
+        .stdin
+        .as_mut()
+        .ok_or(anyhow!("The server's console isn't available!"))?;
+
+    writeln!(stdin, "{command}")?;
+
+    Ok(())
+}
+
+/// Finds the crash report path in a line of server output, if it mentions one.
+pub fn crash_report_in(line: &str) -> Option<PathBuf> {
+    ["crash report has been saved to:", "Crash report saved to:"]
+        .iter()
+        .find_map(|marker| line.split_once(marker))
+        .map(|(_, path)| path.replace("#@!@#", "").trim().into())
+        .filter(|it: &PathBuf| !it.as_os_str().is_empty())
+}
+
+/// The newest crash report written since `since`, for crashes that weren't logged to
+/// the console.
+pub fn latest_crash_report(dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    fs::read_dir(dir.join("crash-reports"))
+        .ok()?
+        .filter_map(|it| it.ok())
+        .filter_map(|it| Some((it.metadata().ok()?.modified().ok()?, it.path())))
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// Waits for a server to exit, killing it if it takes longer than `timeout`. Returns its
+/// exit code and whether it exited by itself.
+pub fn wait_for_exit(child: &mut Child, timeout: Duration) -> Result<(Option<i32>, bool)> {
+    let start = Instant::now();
+
+    while start.elapsed() < timeout {
+        if let Some(status) = child.try_wait()? {
+            return Ok((status.code(), true));
+        }
+
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    Ok((terminate_child(child)?.code(), false))
+}
+
+/// Installs a server unless it's already installed, then returns the Java executable and
+/// arguments to start it without its GUI.
+pub async fn prepare_server(
+    opts: &InstallOptions,
+    reinstall: bool,
+) -> Result<(String, Vec<String>)> {
+    let dir = &opts.target;
+    let args_file = dir
+        .join(NEOFORGE_LIBRARY_DIR)
+        .join(&opts.neo)
+        .join(SERVER_ARGS_FILE);
+
+    if reinstall || !args_file.exists() {
+        install(opts).await?;
+    } else {
+        println!("Reusing the server installed in {}.", dir.display());
+        write_server_config(dir, &opts.server)?;
+    }
+
+    let args = server_args(dir, Some(&opts.neo), true).await?;
+    let java = server_java(&opts.java, &args).await?;
+
+    Ok((java, args))
+}
+
+/// Installs (or reuses) a server, waits for it to finish starting, then stops it.
+pub async fn smoke_test(opts: &SmokeTestOptions) -> Result<SmokeTestReport> {
+    let neo = &opts.install.neo;
+    let dir = &opts.install.target;
+    let setup_start = Instant::now();
+    let started_at = SystemTime::now();
+
+    let setup = prepare_server(&opts.install, opts.reinstall)
+        .await
+        .and_then(|(java, args)| spawn_server(dir, &java, &args));
+
+    // A server that can't be set up still gets a report, so CI has something to show.
+    let (mut child, lines) = match setup {
+        Ok(it) => it,
+        Err(err) if interrupted() => return Err(err),
+
+        Err(err) => {
+            let report = SmokeTestReport {
+                neo: neo.clone(),
+                passed: false,
+                outcome: SmokeTestOutcome::SetupFailed,
+                message: format!("The server couldn't be set up: {err:#}"),
+                startup_time: None,
+                duration: setup_start.elapsed().as_secs_f64(),
+                exit_code: None,
+                crash_report: None,
+            };
+
+            report.write_to(opts)?;
+
+            return Ok(report);
+        }
+    };
+
+    let start = Instant::now();
+
+    let mut crash_report = None;
+    let mut startup_time = None;
+    let mut timed_out = false;
+
+    loop {
+        if interrupted() {
+            terminate_child(&mut child)?;
+            return Err(anyhow!("The smoke test was interrupted!"));
+        }
+
+        let Some(remaining) = opts.startup_timeout.checked_sub(start.elapsed()) else {
+            timed_out = true;
+            break;
+        };
+
+        match lines.recv_timeout(remaining.min(Duration::from_millis(100))) {
+            Ok(line) => {
+                crash_report = crash_report.or(crash_report_in(&line));
+
+                if line.contains("Done (") && line.contains("For help") {
+                    startup_time = Some(start.elapsed().as_secs_f64());
+                    break;
+                }
+            }
+
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    let (outcome, message, exit_code) = match startup_time {
+        Some(time) => {
+            println!("The server started in {time:.1}s, stopping it...");
+
+            // If the console is gone the server is already on its way out, which the
+            // exit check below reports.
+            let _ = send_command(&mut child, "stop");
+
+            match wait_for_exit(&mut child, STOP_TIMEOUT)? {
+                (Some(0), true) => (
+                    SmokeTestOutcome::Passed,
+                    "The server started and stopped".into(),
+                    Some(0),
+                ),
+
+                (code, true) => (
+                    SmokeTestOutcome::StopFailed,
+                    format!(
+                        "The server exited with code {} while stopping",
+                        code.unwrap_or(-1)
+                    ),
+                    code,
+                ),
+
+                (code, false) => (
+                    SmokeTestOutcome::StopFailed,
+                    format!("The server didn't stop within {}s", STOP_TIMEOUT.as_secs()),
+                    code,
+                ),
+            }
+        }
+
+        None if timed_out => {
+            let (code, _) = wait_for_exit(&mut child, Duration::ZERO)?;
+
+            (
+                SmokeTestOutcome::TimedOut,
+                format!(
+                    "The server didn't start within {}s",
+                    opts.startup_timeout.as_secs()
+                ),
+                code,
+            )
+        }
+
+        None => {
+            let (code, _) = wait_for_exit(&mut child, STOP_TIMEOUT)?;
+
+            (
+                SmokeTestOutcome::Crashed,
+                format!(
+                    "The server exited with code {} before it started",
+                    code.unwrap_or(-1)
+                ),
+                code,
+            )
+        }
+    };
+
+    let crash_report = crash_report
+        .map(|it| match it.is_absolute() {
+            true => it,
+            false => dir.join(it),
+        })
+        .or_else(|| latest_crash_report(dir, started_at));
+
+    let report = SmokeTestReport {
+        neo: neo.clone(),
+        passed: outcome == SmokeTestOutcome::Passed,
+        outcome,
+        message,
+        startup_time,
+        duration: setup_start.elapsed().as_secs_f64(),
+        exit_code,
+        crash_report,
+    };
+
+    report.write_to(opts)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crash_report_from_dedicated_server() {
+        let line = "[12:41:07] [Server thread/ERROR] [minecraft/MinecraftServer]: This crash report has been saved to: /srv/mc/crash-reports/crash-2024-11-02_12.41.07-server.txt";
+
+        assert_eq!(
+            crash_report_in(line),
+            Some("/srv/mc/crash-reports/crash-2024-11-02_12.41.07-server.txt".into())
+        );
+    }
+
+    #[test]
+    fn crash_report_with_marker() {
+        let line = "#@!@# Game crashed! Crash report saved to: #@!@# ./crash-reports/crash-2024-11-02_12.41.07-fml.txt";
+
+        assert_eq!(
+            crash_report_in(line),
+            Some("./crash-reports/crash-2024-11-02_12.41.07-fml.txt".into())
+        );
+    }
+
+    #[test]
+    fn no_crash_report() {
+        assert_eq!(
+            crash_report_in(
+                "[12:41:07] [Server thread/INFO] [minecraft/DedicatedServer]: Done (4.812s)! For help, type \"help\""
+            ),
+            None
+        );
+
+        assert_eq!(crash_report_in("Crash report saved to: #@!@#"), None);
+    }
+}
@@ -1,5 +1,8 @@
 use anyhow::Result;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 pub fn make_path_and_create(path: impl Into<PathBuf>) -> Result<PathBuf> {
     let path = path.into();
@@ -7,3 +10,60 @@ pub fn make_path_and_create(path: impl Into<PathBuf>) -> Result<PathBuf> {
     fs::create_dir_all(&path)?;
     Ok(path)
 }
+
+/// The temp directory a JVM is given with `-Djava.io.tmpdir`, if any. Java expects it to
+/// exist already.
+pub fn jvm_tmp_dir(jvm_args: &[String]) -> Option<PathBuf> {
+    jvm_args
+        .iter()
+        .rev()
+        .find_map(|it| it.strip_prefix("-Djava.io.tmpdir="))
+        .map(PathBuf::from)
+}
+
+/// Removes the `.installer` directory after an install. Failed installs keep their
+/// processor logs and argfiles so the failure can be inspected.
+pub fn clean_installer_dir(base_path: &Path, keep: bool, success: bool) -> Result<()> {
+    if keep || !base_path.exists() {
+        return Ok(());
+    }
+
+    if success {
+        fs::remove_dir_all(base_path)?;
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(base_path)? {
+        let entry = entry?;
+
+        if entry.file_name() == "logs" || entry.file_name() == "args" {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds an executable by name on the `PATH`, or returns the path as-is if it has a directory.
+pub fn find_executable(name: impl AsRef<Path>) -> Option<PathBuf> {
+    let name = name.as_ref();
+
+    if name.components().count() > 1 {
+        return name.exists().then(|| name.to_path_buf());
+    }
+
+    let exts: &[&str] = if cfg!(windows) { &["exe", ""] } else { &[""] };
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .flat_map(|dir| {
+            exts.iter()
+                .map(move |ext| dir.join(name).with_extension(ext))
+        })
+        .find(|it| it.is_file())
+}